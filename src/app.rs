@@ -0,0 +1,279 @@
+use log::{error, info};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use pixels::{Error, Pixels, SurfaceTexture};
+use winit::dpi::LogicalSize;
+use winit::event::{Event, VirtualKeyCode};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::WindowBuilder;
+use winit_input_helper::WinitInputHelper;
+
+use crate::gui::{Gui, GuiInput, SelectedCell};
+use crate::debug::DebugInfo;
+use crate::world::{World, Schedule};
+use crate::cell::{Cell, Organism};
+use crate::clock::{SimClock, Speed};
+use crate::record::Recorder;
+
+const SCREEN_WIDTH: u32 = 1024;
+const SCREEN_HEIGHT: u32 = 576;
+const SAVE_PATH: &str = "fishtank.sav";
+const RECORD_PATH: &str = "fishtank.gif";
+// keep one tick in this many, and show each for this many hundredths of a second
+const RECORD_EVERY: u32 = 4;
+const RECORD_DELAY: u16 = 6;
+// frames kept at most, a minute and a half of GIF
+const RECORD_LIMIT: usize = 1500;
+const TICKS_PER_SECOND: u32 = 60;
+
+
+pub fn run(world_width: usize, world_height: usize) -> Result<(), Error> {
+    let event_loop = EventLoop::new();
+    let mut input = WinitInputHelper::new();
+    let window = {
+        let size = LogicalSize::new(SCREEN_WIDTH as f64, SCREEN_HEIGHT as f64);
+        WindowBuilder::new()
+            .with_title("Hello Pixels + Dear ImGui")
+            .with_inner_size(size)
+            .build(&event_loop)
+            .unwrap()
+    };
+    let mut world = World::new(world_width, world_height);
+    let mut pixels = {
+        let window_size = window.inner_size();
+        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
+        Pixels::new(world.width() as u32, world.height() as u32, surface_texture)?
+    };
+    let mut gui = Gui::new(&window, &pixels);
+    let mut debug = DebugInfo::new();
+    let mut selected_cell = SelectedCell::Sand;
+    let mut smooth_lighting = false;
+    let mut block_spawn = false;
+    let mut block_keys = false;
+    let mut clock = SimClock::new(TICKS_PER_SECOND);
+    let mut save = false;
+    let mut load = false;
+    let mut recording = false;
+    let mut recorder: Option<Recorder> = None;
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    let large_spawn: Vec::<(usize, usize)> = vec! [
+           (1,0), (2,0), (3,0), (4,0), (5,0), (7,0), (7,0),
+           (1,1), (2,1), (3,1), (4,1), (5,1), (6,1), (7,1),
+    (0,2), (1,2), (2,2), (3,2), (4,2), (5,2), (6,2), (7,2), (8,2),
+    (0,3), (1,3), (2,3), (3,3), (4,3), (5,3), (6,3), (7,3), (8,3),
+    (0,4), (1,4), (2,4), (3,4), (4,4), (5,4), (6,4), (7,4), (8,4),
+    (0,5), (1,5), (2,5), (3,5), (4,5), (5,5), (6,5), (7,5), (8,5),
+    (0,6), (1,6), (2,6), (3,6), (4,6), (5,6), (6,6), (7,6), (8,6),
+           (1,7), (2,7), (3,7), (4,7), (5,7), (6,7), (7,7),
+           (1,8), (2,8), (3,8), (4,8), (5,8), (6,8), (7,8),
+    ];
+
+    let small_spawn: Vec::<(usize, usize)> = vec! [
+           (1,0), (2,0), (3,0), (4,0),
+    (0,1), (1,1), (2,1), (3,1), (4,1), (5,1),
+    (0,2), (1,2), (2,2), (3,2), (4,2), (5,2),
+    (0,3), (1,3), (2,3), (3,3), (4,3), (5,3),
+           (1,4), (2,4), (3,4), (4,4),
+    ];
+
+    event_loop.run(move |event, _, control_flow| {
+        // Draw the current frame
+        if let Event::RedrawRequested(_) = event {
+            // Draw the world
+            let frame = pixels.get_frame();
+            world.draw(frame, smooth_lighting);
+
+            // Prepare Dear ImGui
+            gui.prepare(&window).expect("gui.prepare() failed");
+
+            // Render everything together
+            let render_result = pixels.render_with(|encoder, render_target, context| {
+                // Render the world texture
+                context.scaling_renderer.render(encoder, render_target);
+
+                // Render Dear ImGui
+                let input = GuiInput { debug: &debug, history: world.history(), clock: &mut clock };
+                let gui_state = gui.render(&window, encoder, render_target, context, input);
+                selected_cell = gui_state.selected_cell;
+                smooth_lighting = gui_state.smooth_lighting;
+                block_spawn = gui_state.block_spawn;
+                block_keys = gui_state.block_keys;
+                save = gui_state.save;
+                load = gui_state.load;
+                recording = gui_state.recording;
+                world.set_scan_order(gui_state.scan_order);
+                world.set_boundary(gui_state.boundary);
+                // the sun sweeps by itself during the day, so only set it when it was moved
+                if gui_state.sun_angle != world.sun_angle() {
+                    world.set_sun_angle(gui_state.sun_angle);
+                }
+                world.set_light_blur_radius(gui_state.light_blur_radius);
+                if gui_state.day_cycle != world.day_cycle() {
+                    world.set_day_cycle(gui_state.day_cycle);
+                }
+                world.set_schedule(if gui_state.parallel { Schedule::Checkerboard { threads } } else { Schedule::Serial });
+            });
+
+            // Basic error handling
+            if render_result
+                .map_err(|e| error!("pixels.render() failed: {}", e))
+                .is_err()
+            {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+
+            if recording && recorder.is_none() {
+                let mut started = Recorder::new(world.width(), world.height(), RECORD_EVERY);
+                started.set_limit(RECORD_LIMIT);
+                recorder = Some(started);
+            }
+            else if !recording {
+                if let Some(finished) = recorder.take() {
+                    match finished.write_to(RECORD_PATH, 1, RECORD_DELAY) {
+                        Ok(()) => info!("recorded {} frames to {}", finished.len(), RECORD_PATH),
+                        Err(e) => error!("writing {} failed: {}", RECORD_PATH, e)
+                    }
+                }
+            }
+            debug.recorded_frames = recorder.as_ref().map(|r| (r.len(), r.is_full()));
+
+            if save {
+                let saved = File::create(SAVE_PATH).and_then(|f| {
+                    let mut w = BufWriter::new(f);
+                    world.save(&mut w)?;
+                    w.flush()
+                });
+                match saved {
+                    Ok(()) => info!("saved to {}", SAVE_PATH),
+                    Err(e) => error!("saving {} failed: {}", SAVE_PATH, e)
+                }
+            }
+            if load {
+                match File::open(SAVE_PATH).and_then(|f| World::load(&mut BufReader::new(f))) {
+                    // the pixel buffer is sized for the current world
+                    Ok(loaded) if loaded.width() != world.width() || loaded.height() != world.height() => {
+                        error!("{} is {}x{}, not {}x{}", SAVE_PATH, loaded.width(), loaded.height(), world.width(), world.height())
+                    },
+                    Ok(loaded) => {
+                        world = loaded;
+                        info!("loaded {}", SAVE_PATH);
+                    },
+                    Err(e) => error!("loading {} failed: {}", SAVE_PATH, e)
+                }
+            }
+        }
+
+        // Handle input events
+        gui.handle_event(&window, &event);
+        if input.update(event) {
+            // Close events
+            if input.key_pressed(VirtualKeyCode::Escape) || input.quit() {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+
+            // Simulation clock
+            if !block_keys {
+                if input.key_pressed(VirtualKeyCode::Space) {
+                    clock.toggle_paused();
+                }
+                if input.key_pressed(VirtualKeyCode::Period) {
+                    clock.step();
+                }
+                if input.key_pressed(VirtualKeyCode::Key1) {
+                    clock.set_speed(Speed::Normal);
+                }
+                if input.key_pressed(VirtualKeyCode::Key2) {
+                    clock.set_speed(Speed::Double);
+                }
+                if input.key_pressed(VirtualKeyCode::Key3) {
+                    clock.set_speed(Speed::Ten);
+                }
+                if input.key_pressed(VirtualKeyCode::Key4) {
+                    clock.set_speed(Speed::Max);
+                }
+            }
+
+            // Resize the window
+            if let Some(size) = input.window_resized() {
+                pixels.resize(size.width, size.height);
+            }
+
+            let mut mouse_pos = None;
+            if let Some(mpos) = input.mouse() {
+                mouse_pos = pixels.window_pos_to_pixel(mpos).ok();
+            }
+            debug.world_pos = mouse_pos;
+
+            debug.spawning = false;
+            if input.mouse_held(0) && !block_spawn {
+                debug.spawning = true;
+                if let Some(pos) = mouse_pos {
+                    match selected_cell {
+                        SelectedCell::Sand => {
+                            for d in &large_spawn {
+                                world.spawn((pos.0 + d.0, pos.1 + d.1), Cell::Sand);
+                            }
+                        },
+                        SelectedCell::Stone => {
+                            for d in &small_spawn {
+                                world.spawn((pos.0 + d.0, pos.1 + d.1), Cell::Stone); 
+                            }
+                        },
+                        SelectedCell::Fizzer => world.spawn((pos.0, pos.1), Cell::Fizzer),
+                        SelectedCell::Lamp => world.spawn((pos.0, pos.1), Cell::Lamp),
+                        SelectedCell::BottomFeeder => world.spawn((pos.0, pos.1), Cell::BottomFeeder),
+                        SelectedCell::Seed => {
+                            for d in &small_spawn {
+                                world.spawn((pos.0 + d.0, pos.1 + d.1), Cell::Seed); 
+                            }
+                        },
+                        SelectedCell::KelpSeed => world.spawn((pos.0, pos.1), Cell::KelpSeed),
+                        SelectedCell::Fish => {
+                            world.spawn_organism((pos.0, pos.1), Organism::new_fish());
+                        },
+                        SelectedCell::Algae => {
+                            world.spawn_organism((pos.0, pos.1), Organism::new_algae());
+                        },
+                        SelectedCell::Worm => {
+                            world.spawn_organism((pos.0, pos.1), Organism::new_worm());
+                        },
+                    };
+                }
+            }
+            else if input.mouse_held(1) && !block_spawn {
+                debug.spawning = true;
+                if let Some(pos) = mouse_pos {
+                    for d in &small_spawn {
+                        world.spawn((pos.0 + d.0, pos.1 + d.1), Cell::Water); 
+                    }
+                }
+            }
+            
+            // recorded as the world ticks, so pausing or a slow frame rate doesn't change the recording
+            clock.run(|| {
+                world.update();
+                if let Some(recorder) = &mut recorder {
+                    if recorder.due() && !recorder.is_full() {
+                        recorder.frame(&world.render_to_rgba(smooth_lighting));
+                    }
+                    else {
+                        recorder.skip();
+                    }
+                }
+            });
+            debug.tick = world.tick();
+            debug.awake_chunks = world.awake_chunks();
+            debug.scan_order = world.scan_order();
+            debug.boundary = world.boundary();
+            debug.stray_writes = world.stray_writes();
+            debug.sun_angle = world.sun_angle();
+            debug.light_blur_radius = world.light_blur_radius();
+            debug.day_cycle = world.day_cycle();
+            debug.time_of_day = world.time_of_day();
+            window.request_redraw();
+        }
+    });
+}
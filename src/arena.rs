@@ -0,0 +1,132 @@
+/// Refers to a value in an `Arena`. Handles of removed values are reused, so a handle only means
+/// something while whatever holds it is still around.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Handle(u32);
+
+impl Handle {
+    /// Refers to nothing, for head cells made outside a world.
+    pub(crate) const DETACHED: Handle = Handle(u32::MAX);
+
+    pub(crate) fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Values stored in slots and looked up by `Handle`, reusing the slots of removed values.
+pub(crate) struct Arena<T> {
+    slots: Vec::<Option<T>>,
+    free: Vec::<u32>
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: vec!(),
+            free: vec!()
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> Handle {
+        match self.free.pop() {
+            Some(i) => {
+                self.slots[i as usize] = Some(value);
+                Handle(i)
+            },
+            None => {
+                self.slots.push(Some(value));
+                Handle(self.slots.len() as u32 - 1)
+            }
+        }
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.slots.get(handle.index()).and_then(Option::as_ref)
+    }
+
+    /// Move a value out, leaving its slot empty but reserved until `put` or `retain`.
+    pub fn take(&mut self, handle: Handle) -> Option<T> {
+        self.slots.get_mut(handle.index()).and_then(Option::take)
+    }
+
+    /// Put a value back into the slot of a handle from `insert`.
+    pub fn put(&mut self, handle: Handle, value: T) {
+        self.slots[handle.index()] = Some(value);
+    }
+
+    /// Every value with its handle, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.slots.iter().enumerate().filter_map(|(i, v)| v.as_ref().map(|v| (Handle(i as u32), v)))
+    }
+
+    /// Number of slots, filled or not. Every handle from this arena is below it.
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Move every value out with its handle, in slot order, leaving the arena empty.
+    pub fn drain(&mut self) -> impl Iterator<Item = (Handle, T)> + '_ {
+        self.free.clear();
+        self.slots.drain(..).enumerate().filter_map(|(i, v)| v.map(|v| (Handle(i as u32), v)))
+    }
+
+    /// Free every slot whose handle isn't in `live`.
+    pub fn retain(&mut self, live: impl Iterator<Item = Handle>) {
+        // slots already on the free list count as kept so they aren't freed twice
+        let mut keep = vec![false; self.slots.len()];
+        for handle in live {
+            // a detached handle has no slot to keep
+            if let Some(k) = keep.get_mut(handle.index()) {
+                *k = true;
+            }
+        }
+        for &i in &self.free {
+            keep[i as usize] = true;
+        }
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            if !keep[i] {
+                *slot = None;
+                self.free.push(i as u32);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_are_reused() {
+        let mut arena = Arena::new();
+        let a = arena.insert('a');
+        let b = arena.insert('b');
+        let c = arena.insert('c');
+        arena.retain([a, c].iter().copied());
+        assert_eq!(arena.get(b), None);
+        // the freed slot is filled before the arena grows
+        let d = arena.insert('d');
+        assert_eq!(d, b);
+        assert_eq!(arena.get(d), Some(&'d'));
+        assert_eq!(arena.insert('e').index(), 3);
+        assert_eq!(arena.slot_count(), 4);
+    }
+
+    #[test]
+    fn taken_slots_stay_reserved() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        assert_eq!(arena.take(a), Some(1));
+        assert_ne!(arena.insert(2), a);
+        arena.put(a, 3);
+        assert_eq!(arena.iter().map(|(_, v)| *v).collect::<Vec::<i32>>(), vec![3, 2]);
+    }
+
+    #[test]
+    fn retain_ignores_detached_handles() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        arena.retain([a, Handle::DETACHED].iter().copied());
+        assert_eq!(arena.get(a), Some(&1));
+        assert_eq!(arena.get(Handle::DETACHED), None);
+    }
+}
@@ -1,4 +1,18 @@
-use fishtank::run;
+use fishtank::{run, log_to_stderr, DEFAULT_WORLD_WIDTH, DEFAULT_WORLD_HEIGHT};
+use log::LevelFilter;
+use std::env;
+
 pub fn main() {
-    run();
-}
\ No newline at end of file
+    // usage: fishtank [WIDTH HEIGHT]
+    let args: Vec::<String> = env::args().collect();
+    let (width, height) = match (args.get(1), args.get(2)) {
+        (Some(w), Some(h)) => (
+            w.parse::<usize>().ok().filter(|&w| w > 0).expect("WIDTH must be a positive integer"),
+            h.parse::<usize>().ok().filter(|&h| h > 0).expect("HEIGHT must be a positive integer")
+        ),
+        _ => (DEFAULT_WORLD_WIDTH, DEFAULT_WORLD_HEIGHT)
+    };
+    // saving, loading and recording report how they went in the log
+    log_to_stderr(LevelFilter::Info);
+    run(width, height);
+}
@@ -0,0 +1,9 @@
+use fishtank::run_headless;
+use std::process;
+
+pub fn main() {
+    if let Err(e) = run_headless() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
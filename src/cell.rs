@@ -1,14 +1,17 @@
 use rand::prelude::*;
+use std::io::{self, Read, Write};
 use crate::world::Cells;
+use crate::arena::{Arena, Handle};
+use crate::save;
 
 
 #[inline]
-fn once_every(n: u32) -> bool {
-    thread_rng().gen_range(0..=n) == n
+fn once_every(cells: &mut Cells, n: u32) -> bool {
+    cells.rng().gen_range(0..=n) == n
 }
 
 #[inline]
-fn random_direction() -> (i32, i32) {
+fn random_direction(cells: &mut Cells) -> (i32, i32) {
     let dir_choices : Vec::<(i32, i32)> = vec!(
         (0, 1),
         (0, -1),
@@ -19,47 +22,52 @@ fn random_direction() -> (i32, i32) {
         (-1, 1),
         (-1, -1),
     );
-    *dir_choices.choose(&mut thread_rng()).unwrap()
+    *dir_choices.choose(cells.rng()).unwrap()
 }
 
-#[derive(Clone, PartialEq, Eq, new)]
+/// State of a single algae cell.
+#[derive(Clone, Debug, PartialEq, Eq, new)]
 pub struct AlgaeUpdate {
     #[new(value = "1")]
     e: i32
 }
 
 impl AlgaeUpdate {
-    pub fn update_algae(mut self, cells: &mut Cells) {
+    pub fn energy(&self) -> i32 {
+        self.e
+    }
+
+    pub fn update_algae(mut self, handle: Handle, cells: &mut Cells) {
 
         // die
-        if self.e < 0 && once_every(320) {
+        if self.e < 0 && once_every(cells, 320) {
             cells.set(0, 0, Cell::Decay);
             return;
         }
 
-        if once_every(1280) {
+        if once_every(cells, 1280) {
             self.e -= 1;
         }
 
         // lose energy without light
-        if once_every(320) && cells.get_light(0, 0) <= 8 {
+        if once_every(cells, 320) && cells.get_light(0, 0) <= 8 {
             self.e -= 1
         }
 
         // reproduce
-        if self.e > 0 && once_every(1280) {
-            let (x, y) = random_direction();
+        if self.e > 0 && once_every(cells, 1280) {
+            let (x, y) = random_direction(cells);
             if cells.get(x, y) == Some(&Cell::Water) {
                 self.e -= 1;
-                if thread_rng().gen::<bool>() {
-                    cells.set(x, y, Cell::new_algae());
+                if cells.rng().gen::<bool>() {
+                    cells.new_organism(x, y, Organism::new_algae());
                 }
             }
         }
 
         // eat
-        if once_every(10) {
-            for (x, y) in vec!((0, 1), (0, -1), (1, 0), (1, 1), (1, -1), (-1, 0), (-1, 1), (-1, -1),) {
+        if once_every(cells, 10) {
+            for (x, y) in [(0, 1), (0, -1), (1, 0), (1, 1), (1, -1), (-1, 0), (-1, 1), (-1, -1)] {
                 if cells.get(x, y) == Some(&Cell::Nitrogen){
                     cells.set(x, y, Cell::Bubble);
                     self.e += 1;
@@ -69,8 +77,8 @@ impl AlgaeUpdate {
 
         // move
         let (mut nx, mut ny) = (0, 0);
-        if once_every(360) {
-            let (x, y) = random_direction(); // TODO drift according to water current
+        if once_every(cells, 360) {
+            let (x, y) = random_direction(cells); // TODO drift according to water current
             if cells.get(x, y) == Some(&Cell::Water) {
                 cells.set(0, 0, Cell::Water);
                 nx = x;
@@ -78,20 +86,25 @@ impl AlgaeUpdate {
             }
         }
 
-        cells.set(nx, ny, Cell::Algae { update: self });
+        cells.move_organism(nx, ny, handle, Organism::Algae(self));
         cells.ignore(nx, ny);
     }
 }
 
-#[derive(Clone, PartialEq, Eq, new)]
+/// State of a single plant cell.
+#[derive(Clone, Debug, PartialEq, Eq, new)]
 pub struct PlantUpdate {
     #[new(value = "1")]
     e: i32
 }
 
 impl PlantUpdate {
-    pub fn update_plant(mut self, cells:&mut Cells) {
-        if self.e < 0 && once_every(180) {
+    pub fn energy(&self) -> i32 {
+        self.e
+    }
+
+    pub fn update_plant(mut self, handle: Handle, cells:&mut Cells) {
+        if self.e < 0 && once_every(cells, 180) {
             match cells.get(0, -1) {
                 Some(Cell::Plant {..}) => {
                     
@@ -104,55 +117,57 @@ impl PlantUpdate {
         }
 
         // lose energy without light
-        if once_every(320) && cells.get_light(0, 0) <= 8 {
+        if once_every(cells, 320) && cells.get_light(0, 0) <= 8 {
             self.e -= 1
         }
 
-        if self.e > 0 && once_every(180) {
-            let x =  *vec!(-1, 0, 0, 0, 1).choose(&mut thread_rng()).unwrap();
-            match cells.get(x, -1) {
-                Some(Cell::Water) => {
-                    self.e -= 1;
-                    cells.set(x, -1, Cell::new_plant());
-                    cells.ignore(x, -1);
-                },
-                _ => {}
+        if self.e > 0 && once_every(cells, 180) {
+            let x =  *[-1, 0, 0, 0, 1].choose(cells.rng()).unwrap();
+            if let Some(Cell::Water) = cells.get(x, -1) {
+                self.e -= 1;
+                cells.new_organism(x, -1, Organism::new_plant());
+                cells.ignore(x, -1);
             }
         }       
-        cells.set(0, 0, Cell::Plant { update: self }); 
+        cells.put_organism(handle, Organism::Plant(self));
         cells.ignore(0, 0);
     }
 }
 
-#[derive(Clone, PartialEq, Eq, new)]
+/// State of a single kelp stalk cell.
+#[derive(Clone, Debug, PartialEq, Eq, new)]
 pub struct KelpUpdate {
     #[new(value = "1")]
     e: i32
 }
 
 impl KelpUpdate {
-    pub fn update_kelp(mut self, cells: &mut Cells) {
+    pub fn energy(&self) -> i32 {
+        self.e
+    }
+
+    pub fn update_kelp(mut self, handle: Handle, cells: &mut Cells) {
         // die
-        if self.e < 0 && once_every(320) {
+        if self.e < 0 && once_every(cells, 320) {
             cells.set(0, 0, Cell::Decay);
             return;
         }
 
         // lose energy without light
-        if once_every(320) && cells.get_light(0, 0) <= 8 {
+        if once_every(cells, 320) && cells.get_light(0, 0) <= 8 {
             self.e -= 1
         }
 
-        if once_every(60) && self.e > 0 {
+        if once_every(cells, 60) && self.e > 0 {
 
             // stop growing randomly
-            if once_every(20) && self.e > 0 {
+            if once_every(cells, 20) && self.e > 0 {
                 self.e -= 1;
             }
             else {
                 if cells.get(0, -1) == Some(&Cell::Water) {
                     self.e -= 1;
-                    cells.set(0, -1, Cell::new_kelp());
+                    cells.new_organism(0, -1, Organism::new_kelp());
                 }
                 if cells.current_pos().1 % 2 == 0 {
                     cells.set(1, 0, Cell::KelpLeaf);
@@ -167,12 +182,13 @@ impl KelpUpdate {
             }            
         }
 
-        cells.set(0, 0, Cell::Kelp { update: self});
+        cells.put_organism(handle, Organism::Kelp(self));
         cells.ignore(0, 0);
     }
 }
 
-#[derive(Clone, PartialEq, Eq, new)]
+/// State of a worm, kept in the arena and pointed to by its head cell.
+#[derive(Clone, Debug, PartialEq, Eq, new)]
 pub struct WormUpdate {
     #[new(value = "0")]
     dx: i32,
@@ -185,24 +201,37 @@ pub struct WormUpdate {
 }
 
 impl WormUpdate {
-    
-    fn update_worm(mut self, cells: &mut Cells) {
+    pub fn energy(&self) -> i32 {
+        self.e
+    }
+
+    /// Direction the worm is crawling in.
+    pub fn direction(&self) -> (i32, i32) {
+        (self.dx, self.dy)
+    }
+
+    /// Body segments, each relative to the one before it, starting from the head.
+    pub fn body(&self) -> &[(i32, i32)] {
+        &self.body
+    }
+
+    fn update_worm(mut self, handle: Handle, cells: &mut Cells) {
 
         // eat
-        if once_every(2) {
-            let (dx, dy) = random_direction();
+        if once_every(cells, 2) {
+            let (dx, dy) = random_direction(cells);
             match cells.get(dx, dy) {
                 Some(Cell::Algae{..}) | Some(Cell::Decay) => {
                     self.e += 1;
                     cells.set(dx, dy, Cell::Water);
-                    self.body.push(self.body.last().unwrap_or(&(0, 0)).clone());
+                    self.body.push(*self.body.last().unwrap_or(&(0, 0)));
                 },
                 _ => {}
             }
         }
 
         // check energy
-        if once_every(360) {
+        if once_every(cells, 360) {
             self.e -= 1;
             if self.e <= 0 {
                 let mut body_x = 0;
@@ -218,10 +247,10 @@ impl WormUpdate {
         }
 
         // swim
-        if once_every(5) {
-            if once_every(20) {
+        if once_every(cells, 5) {
+            if once_every(cells, 20) {
                 // change direction
-                let tmp = random_direction();
+                let tmp = random_direction(cells);
                 self.dx = tmp.0;
                 self.dy = tmp.1; 
             }
@@ -255,17 +284,19 @@ impl WormUpdate {
                 body_x += x;
                 body_y += y;
             }
-            self.body.insert(0, (self.dx * -1, self.dy * -1));
+            self.body.insert(0, (-self.dx, -self.dy));
             self.body.pop();
-            cells.set(self.dx, self.dy, Cell::Worm { update: self});
+            let (dx, dy) = (self.dx, self.dy);
+            cells.move_organism(dx, dy, handle, Organism::Worm(self));
         }
         else {            
-            cells.set(0, 0, Cell::Worm { update: self});
+            cells.put_organism(handle, Organism::Worm(self));
         }
     }
 }
 
-#[derive(Clone, PartialEq, Eq, new)]
+/// State of a fish, kept in the arena and pointed to by its head cell.
+#[derive(Clone, Debug, PartialEq, Eq, new)]
 pub struct FishUpdate {
     #[new(value = "0")]
     dx: i32,
@@ -278,23 +309,40 @@ pub struct FishUpdate {
 }
 
 impl FishUpdate {
+    pub fn energy(&self) -> i32 {
+        self.e
+    }
 
-    fn update_fish(mut self, cells: &mut Cells) {
+    /// Direction the fish is swimming in.
+    pub fn direction(&self) -> (i32, i32) {
+        (self.dx, self.dy)
+    }
+
+    /// Body cell offsets from the head, for a fish facing right. Mirrored when swimming left.
+    pub fn body(&self) -> &[(i32, i32)] {
+        &self.body
+    }
+
+    fn update_fish(mut self, handle: Handle, cells: &mut Cells) {
 
         // eat
-        if once_every(2) {
-            let (dx, dy) = random_direction();
+        if once_every(cells, 2) {
+            let (dx, dy) = random_direction(cells);
             match cells.get(dx, dy) {
                 Some(Cell::Plant {..}) | Some(Cell::Algae{..}) | Some(Cell::Kelp {..}) | Some(Cell::KelpLeaf) => {
                     self.e += 1;
                     cells.set(dx, dy, Cell::Water);
-                    if once_every(4) && cells.get(0, 1) == Some(&Cell::Water){
+                    if once_every(cells, 4) && cells.get(0, 1) == Some(&Cell::Water){
                         cells.set(0, 1, Cell::Decay);
                     }
                 },
-                Some(Cell::Worm { update: u }) => {
+                Some(Cell::Worm { handle: worm }) => {
                     self.e += 2;
-                    for (x , y) in u.body.clone() {
+                    let body = match cells.organism(*worm) {
+                        Some(Organism::Worm(u)) => u.body.clone(),
+                        _ => vec!()
+                    };
+                    for (x , y) in body {
                         cells.set(dx + x, dy + y, Cell::Decay);
                     }
                 }
@@ -303,7 +351,7 @@ impl FishUpdate {
         }
 
         // check energy
-        if once_every(360) {
+        if once_every(cells, 360) {
             self.e -= 1;
             if self.e <= 0 {                
                 let o = if self.dx >= 0 { 1 } else { -1 };
@@ -316,7 +364,7 @@ impl FishUpdate {
         }
 
         // swim
-        if once_every(10) {
+        if once_every(cells, 10) {
 
 
             // clear
@@ -329,9 +377,9 @@ impl FishUpdate {
             }
                      
 
-            if once_every(10) {
+            if once_every(cells, 10) {
                 // change direction
-                let tmp = random_direction();
+                let tmp = random_direction(cells);
                 self.dx = tmp.0;
                 self.dy = tmp.1; 
             }
@@ -367,89 +415,341 @@ impl FishUpdate {
                 for (x, y) in self.body.iter() {            
                     cells.set(self.dx + (*x * o), self.dy + (*y * o), Cell::FishBody);
                 }
-                cells.set(self.dx, self.dy, Cell::Fish { update: self });   
+                let (dx, dy) = (self.dx, self.dy);
+                cells.move_organism(dx, dy, handle, Organism::Fish(self));
             }        
         } 
         else {            
-            cells.set(0, 0, Cell::Fish { update: self }); 
+            cells.put_organism(handle, Organism::Fish(self));
         }
     }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+/// Names of every `Cell` variant, in declaration order.
+pub const CELL_NAMES: [&str; 19] = [
+    "Water", "Sand", "Seed", "Plant", "Fish", "Decay", "BottomFeeder", "Algae", "Nitrogen",
+    "Bubble", "Stone", "Fizzer", "KelpSeed", "Kelp", "KelpLeaf", "FishBody", "Worm", "WormBody",
+    "Lamp"
+];
+
+/// A single cell of the tank. Organisms keep their state in the grid's arena and their head cell
+/// holds a handle to it, so every cell is small and cheap to copy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Cell {
     Water,
     Sand,
     Seed,
-    Plant { update: PlantUpdate },
-    Fish { update: FishUpdate },
+    Plant { handle: Handle },
+    Fish { handle: Handle },
     Decay,
     BottomFeeder,
-    Algae { update: AlgaeUpdate },
+    Algae { handle: Handle },
     Nitrogen,
     Bubble,
     Stone,
     Fizzer,
     KelpSeed,
-    Kelp { update: KelpUpdate },
+    Kelp { handle: Handle },
     KelpLeaf,
     FishBody,
-    Worm { update: WormUpdate},
-    WormBody
+    Worm { handle: Handle },
+    WormBody,
+    /// Stays put and lights up the water around it.
+    Lamp
 }
 
-impl Cell {
+/// State of an organism, looked up through the handle in its head cell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Organism {
+    Plant(PlantUpdate),
+    Fish(FishUpdate),
+    Algae(AlgaeUpdate),
+    Kelp(KelpUpdate),
+    Worm(WormUpdate)
+}
+
+impl Organism {
     pub fn new_fish() -> Self {
-        Cell::Fish {
-            update: FishUpdate::new()
-        }
+        Organism::Fish(FishUpdate::new())
     }
 
     pub fn new_plant() -> Self {
-        Cell::Plant {
-            update: PlantUpdate::new()
-        }
+        Organism::Plant(PlantUpdate::new())
     }
 
     pub fn new_kelp() -> Self {
-        Cell::Kelp {
-            update: KelpUpdate::new()
-        }
+        Organism::Kelp(KelpUpdate::new())
     }
 
     pub fn new_algae() -> Self {
-        Cell::Algae {
-            update: AlgaeUpdate::new()
+        Organism::Algae(AlgaeUpdate::new())
+    }
+
+    pub fn new_worm() -> Self {
+        Organism::Worm(WormUpdate::new())
+    }
+
+    /// A new organism of the kind `cell` is the head of, in its default state.
+    pub fn for_cell(cell: &Cell) -> Option<Self> {
+        match cell {
+            Cell::Plant {..} => Some(Organism::new_plant()),
+            Cell::Fish {..} => Some(Organism::new_fish()),
+            Cell::Algae {..} => Some(Organism::new_algae()),
+            Cell::Kelp {..} => Some(Organism::new_kelp()),
+            Cell::Worm {..} => Some(Organism::new_worm()),
+            _ => None
+        }
+    }
+
+    /// Head cell for this organism, pointing at `handle`.
+    pub fn cell(&self, handle: Handle) -> Cell {
+        match self {
+            Organism::Plant(_) => Cell::Plant { handle },
+            Organism::Fish(_) => Cell::Fish { handle },
+            Organism::Algae(_) => Cell::Algae { handle },
+            Organism::Kelp(_) => Cell::Kelp { handle },
+            Organism::Worm(_) => Cell::Worm { handle }
         }
     }
 
+    pub fn energy(&self) -> i32 {
+        match self {
+            Organism::Plant(update) => update.e,
+            Organism::Fish(update) => update.e,
+            Organism::Algae(update) => update.e,
+            Organism::Kelp(update) => update.e,
+            Organism::Worm(update) => update.e
+        }
+    }
+
+    /// Organism for a character in an ASCII scene, in its default state.
+    pub fn from_char(c: char) -> Option<Self> {
+        let organism = match c {
+            'p' => Organism::new_plant(),
+            'f' => Organism::new_fish(),
+            'a' => Organism::new_algae(),
+            'K' => Organism::new_kelp(),
+            'w' => Organism::new_worm(),
+            _ => return None
+        };
+        Some(organism)
+    }
+
+    /// Write the organism's state to a save, after the tag of its head cell.
+    fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        match self {
+            Organism::Plant(update) => save::write_i32(w, update.e),
+            Organism::Algae(update) => save::write_i32(w, update.e),
+            Organism::Kelp(update) => save::write_i32(w, update.e),
+            Organism::Fish(FishUpdate { dx, dy, e, body }) | Organism::Worm(WormUpdate { dx, dy, e, body }) => {
+                save::write_i32(w, *dx)?;
+                save::write_i32(w, *dy)?;
+                save::write_i32(w, *e)?;
+                save::write_offsets(w, body)
+            }
+        }
+    }
+}
+
+impl Cell {
+    /// A fish head that isn't part of any world yet. `World::spawn` starts a new fish for it.
+    #[deprecated(note = "spawn an `Organism::new_fish()` with `World::spawn_organism`")]
+    pub fn new_fish() -> Self {
+        Cell::Fish { handle: Handle::DETACHED }
+    }
+
+    /// A plant that isn't part of any world yet. `World::spawn` starts a new plant for it.
+    #[deprecated(note = "spawn an `Organism::new_plant()` with `World::spawn_organism`")]
+    pub fn new_plant() -> Self {
+        Cell::Plant { handle: Handle::DETACHED }
+    }
+
+    /// A kelp stem that isn't part of any world yet. `World::spawn` starts a new kelp for it.
+    #[deprecated(note = "spawn an `Organism::new_kelp()` with `World::spawn_organism`")]
+    pub fn new_kelp() -> Self {
+        Cell::Kelp { handle: Handle::DETACHED }
+    }
+
+    /// Algae that isn't part of any world yet. `World::spawn` starts new algae for it.
+    #[deprecated(note = "spawn an `Organism::new_algae()` with `World::spawn_organism`")]
+    pub fn new_algae() -> Self {
+        Cell::Algae { handle: Handle::DETACHED }
+    }
+
+    /// A worm head that isn't part of any world yet. `World::spawn` starts a new worm for it.
+    #[deprecated(note = "spawn an `Organism::new_worm()` with `World::spawn_organism`")]
     pub fn new_worm() -> Self {
-        Cell::Worm {
-            update: WormUpdate::new()
+        Cell::Worm { handle: Handle::DETACHED }
+    }
+
+    /// Index of the variant in `CELL_NAMES`, ignoring any state.
+    pub fn kind(&self) -> usize {
+        match self {
+            Cell::Water => 0,
+            Cell::Sand => 1,
+            Cell::Seed => 2,
+            Cell::Plant {..} => 3,
+            Cell::Fish {..} => 4,
+            Cell::Decay => 5,
+            Cell::BottomFeeder => 6,
+            Cell::Algae {..} => 7,
+            Cell::Nitrogen => 8,
+            Cell::Bubble => 9,
+            Cell::Stone => 10,
+            Cell::Fizzer => 11,
+            Cell::KelpSeed => 12,
+            Cell::Kelp {..} => 13,
+            Cell::KelpLeaf => 14,
+            Cell::FishBody => 15,
+            Cell::Worm {..} => 16,
+            Cell::WormBody => 17,
+            Cell::Lamp => 18,
+        }
+    }
+
+    /// Name of the variant, ignoring any state.
+    pub fn name(&self) -> &'static str {
+        CELL_NAMES[self.kind()]
+    }
+
+    /// Handle of the organism whose head this cell is, if it is one.
+    pub fn handle(&self) -> Option<Handle> {
+        match self {
+            Cell::Plant { handle } | Cell::Fish { handle } | Cell::Algae { handle } | Cell::Kelp { handle } | Cell::Worm { handle } => Some(*handle),
+            _ => None
+        }
+    }
+
+    /// The same head cell pointing at `handle` instead. Cells that aren't organisms are unchanged.
+    pub(crate) fn with_handle(self, handle: Handle) -> Self {
+        match self {
+            Cell::Plant {..} => Cell::Plant { handle },
+            Cell::Fish {..} => Cell::Fish { handle },
+            Cell::Algae {..} => Cell::Algae { handle },
+            Cell::Kelp {..} => Cell::Kelp { handle },
+            Cell::Worm {..} => Cell::Worm { handle },
+            cell => cell
         }
     }
 
+    /// Character used for this cell in ASCII scenes.
+    pub fn to_char(&self) -> char {
+        match self {
+            Cell::Water => '~',
+            Cell::Sand => '.',
+            Cell::Seed => 's',
+            Cell::Plant {..} => 'p',
+            Cell::Fish {..} => 'f',
+            Cell::Decay => 'd',
+            Cell::BottomFeeder => 'b',
+            Cell::Algae {..} => 'a',
+            Cell::Nitrogen => 'n',
+            Cell::Bubble => 'o',
+            Cell::Stone => '#',
+            Cell::Fizzer => 'z',
+            Cell::KelpSeed => 'k',
+            Cell::Kelp {..} => 'K',
+            Cell::KelpLeaf => 'l',
+            Cell::FishBody => 'F',
+            Cell::Worm {..} => 'w',
+            Cell::WormBody => 'W',
+            Cell::Lamp => '*',
+        }
+    }
+
+    /// Cell for a character in an ASCII scene. Organisms come from `Organism::from_char` instead.
+    pub fn from_char(c: char) -> Option<Self> {
+        let cell = match c {
+            '~' => Cell::Water,
+            '.' => Cell::Sand,
+            's' => Cell::Seed,
+            'd' => Cell::Decay,
+            'b' => Cell::BottomFeeder,
+            'n' => Cell::Nitrogen,
+            'o' => Cell::Bubble,
+            '#' => Cell::Stone,
+            'z' => Cell::Fizzer,
+            'k' => Cell::KelpSeed,
+            'l' => Cell::KelpLeaf,
+            'F' => Cell::FishBody,
+            'W' => Cell::WormBody,
+            '*' => Cell::Lamp,
+            _ => return None
+        };
+        Some(cell)
+    }
+
+    /// Write this cell to a save, including the state of the organism it is the head of.
+    pub(crate) fn write(&self, w: &mut impl Write, organisms: &Arena<Organism>) -> io::Result<()> {
+        save::write_u8(w, self.kind() as u8)?;
+        match self.handle() {
+            Some(handle) => organisms.get(handle).ok_or_else(|| save::invalid("organism without state"))?.write(w),
+            None => Ok(())
+        }
+    }
+
+    /// Read a cell written by `Cell::write`, putting any organism state into `organisms`.
+    pub(crate) fn read(r: &mut impl Read, organisms: &mut Arena<Organism>) -> io::Result<Self> {
+        let organism = match save::read_u8(r)? {
+            0 => return Ok(Cell::Water),
+            1 => return Ok(Cell::Sand),
+            2 => return Ok(Cell::Seed),
+            3 => Organism::Plant(PlantUpdate { e: save::read_i32(r)? }),
+            4 => Organism::Fish(FishUpdate {
+                dx: save::read_i32(r)?,
+                dy: save::read_i32(r)?,
+                e: save::read_i32(r)?,
+                body: save::read_offsets(r)?
+            }),
+            5 => return Ok(Cell::Decay),
+            6 => return Ok(Cell::BottomFeeder),
+            7 => Organism::Algae(AlgaeUpdate { e: save::read_i32(r)? }),
+            8 => return Ok(Cell::Nitrogen),
+            9 => return Ok(Cell::Bubble),
+            10 => return Ok(Cell::Stone),
+            11 => return Ok(Cell::Fizzer),
+            12 => return Ok(Cell::KelpSeed),
+            13 => Organism::Kelp(KelpUpdate { e: save::read_i32(r)? }),
+            14 => return Ok(Cell::KelpLeaf),
+            15 => return Ok(Cell::FishBody),
+            16 => Organism::Worm(WormUpdate {
+                dx: save::read_i32(r)?,
+                dy: save::read_i32(r)?,
+                e: save::read_i32(r)?,
+                body: save::read_offsets(r)?
+            }),
+            17 => return Ok(Cell::WormBody),
+            18 => return Ok(Cell::Lamp),
+            _ => return Err(save::invalid("unknown cell type"))
+        };
+        let handle = organisms.insert(organism);
+        Ok(organisms.get(handle).unwrap().cell(handle))
+    }
+
+    /// Advance this cell by one tick. `cells` must be positioned on the cell.
     pub fn update(self, cells: &mut Cells) {
         match self {
             Cell::Sand | Cell::Decay => {
                 self.mv(1, cells);
             },
             Cell::Seed => {
-                self.update_seed(Cell::new_plant(), cells);
+                self.update_seed(Organism::new_plant(), cells);
                 self.mv(1, cells);
             },
-            Cell::Plant { update } => {
-                update.update_plant(cells);
-            }
-            Cell::Fish { update } => {
-                update.update_fish(cells);      
-            }
+            Cell::Plant { handle } | Cell::Fish { handle } | Cell::Algae { handle } | Cell::Kelp { handle } | Cell::Worm { handle } => {
+                // moved out while it updates, and put back wherever it ends up unless it dies
+                match cells.take_organism(handle) {
+                    Some(Organism::Plant(update)) => update.update_plant(handle, cells),
+                    Some(Organism::Fish(update)) => update.update_fish(handle, cells),
+                    Some(Organism::Algae(update)) => update.update_algae(handle, cells),
+                    Some(Organism::Kelp(update)) => update.update_kelp(handle, cells),
+                    Some(Organism::Worm(update)) => update.update_worm(handle, cells),
+                    None => {}
+                }
+            },
             Cell::BottomFeeder => {
                 self.update_bottomfeeder(cells);
             },
-            Cell::Algae { update } => {
-                update.update_algae(cells);
-            },
             Cell::Nitrogen => {
                 self.update_gas(20, cells);
             },
@@ -461,23 +761,21 @@ impl Cell {
                 self.mv(1, cells);
             },
             Cell::KelpSeed => {
-                self.update_seed(Cell::new_kelp(), cells);
+                self.update_seed(Organism::new_kelp(), cells);
                 self.mv(1, cells);
             },
-            Cell::Kelp { update }=> {
-                update.update_kelp(cells);
-            },
-            Cell::Worm { update }=> {
-                update.update_worm(cells);
-            },
-            Cell::Water | Cell::Stone | Cell::KelpLeaf | Cell::FishBody | Cell::WormBody => {
+            Cell::Water | Cell::Stone | Cell::KelpLeaf | Cell::FishBody | Cell::WormBody | Cell::Lamp => {
 
             }
         }
     }
 
     fn mv(&self, yd: i32, cells: &mut Cells) {
-        let (x, _) = random_direction();
+        // don't roll when there's nowhere to go, so a settled bed of sand can sleep
+        if (-1..=1).all(|x| cells.get(x, yd) != Some(&Cell::Water)) {
+            return;
+        }
+        let (x, _) = random_direction(cells);
         if cells.get(x, yd) == Some(&Cell::Water) {
             cells.swap(0, 0, x, yd);
             cells.ignore(x, yd);
@@ -485,19 +783,17 @@ impl Cell {
     }
 
     fn update_fizzer(&self, cells: &mut Cells) {
-        if once_every(20) {
-            if cells.get(0, -1) == Some(&Cell::Water) {
-                cells.set(0, -1, Cell::Bubble);
-            }
-        }        
+        if once_every(cells, 20) && cells.get(0, -1) == Some(&Cell::Water) {
+            cells.set(0, -1, Cell::Bubble);
+        }
     }
 
     fn update_gas(&self, t: u32, cells: &mut Cells) {
-        if !once_every(t) {
+        if !once_every(cells, t) {
             return
         }
 
-        let (x, _) = random_direction();
+        let (x, _) = random_direction(cells);
         match cells.get(x, -1) {
             Some(&Cell::Water) => {
                 cells.swap(0, 0, x, -1);
@@ -506,30 +802,22 @@ impl Cell {
             None => {
                 cells.set(0, 0, Cell::Water);
             },
-            Some(&Cell::Stone) | Some(&Cell::Fish {..}) | Some(&Cell::Worm {..}) | Some(&Cell::FishBody) | Some(&Cell::WormBody) => {
+            Some(&Cell::Stone) | Some(&Cell::Lamp) | Some(&Cell::Fish {..}) | Some(&Cell::Worm {..}) | Some(&Cell::FishBody) | Some(&Cell::WormBody) => {
             },
             _=> {
-                match cells.get(x, -2) {
-                    Some(&Cell::Water) => {
-                        cells.swap(x, -1, x, -2);
-                        cells.swap(0, 0, x, -1);
-                        cells.ignore(x, -1);
-                    },
-                    _ => {}
+                if let Some(&Cell::Water) = cells.get(x, -2) {
+                    cells.swap(x, -1, x, -2);
+                    cells.swap(0, 0, x, -1);
+                    cells.ignore(x, -1);
                 }
             }
         }
     }
 
-    fn update_seed(&self, plant_cell: Cell, cells: &mut Cells) {
-        match cells.get(0, 1) {
-            Some(Cell::Sand) => {
-                if once_every(2) {
-                    cells.set(0, 0, plant_cell);
-                }
-                else {
-                    cells.set(0, 0, Cell::Water);
-                }
+    fn update_seed(&self, plant: Organism, cells: &mut Cells) {
+        match cells.get(0, 1).copied() {
+            Some(Cell::Sand) if once_every(cells, 2) => {
+                cells.new_organism(0, 0, plant);
             },
             Some(Cell::Water) => {
             }
@@ -540,8 +828,8 @@ impl Cell {
     }
 
     fn update_bottomfeeder(&self, cells: &mut Cells) {
-        if once_every(3) {
-            let x =  *vec!(-1, 1).choose(&mut thread_rng()).unwrap();        
+        if once_every(cells, 3) {
+            let x =  *[-1, 1].choose(cells.rng()).unwrap();        
             if cells.get(x, 0) == Some(&Cell::Decay) {
                 cells.set(x, 0, Cell::Nitrogen);
             }
@@ -553,18 +841,18 @@ impl Cell {
             }
         }  
 
-        if !once_every(10) {
+        if !once_every(cells, 10) {
             return;
         }
 
-        let x =  *vec!(-1, 1).choose(&mut thread_rng()).unwrap();  
+        let x =  *[-1, 1].choose(cells.rng()).unwrap();  
         let mut fell = false;
         if cells.get(0, 1) == Some(&Cell::Water) {
             cells.swap(0, 0, 0, 1);
             fell = true;
         }
         else {
-            if thread_rng().gen::<bool>() && cells.get(1, 1) == Some(&Cell::Water) {
+            if cells.rng().gen::<bool>() && cells.get(1, 1) == Some(&Cell::Water) {
                 cells.swap(0, 0, 1, 1);
                 fell = true;
             }
@@ -0,0 +1,171 @@
+use std::time::{Duration, Instant};
+
+/// How fast the simulation runs relative to the clock's ticks per second.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Speed {
+    Normal,
+    Double,
+    Ten,
+    /// As many ticks as fit in a frame.
+    Max
+}
+
+impl Speed {
+    fn multiplier(self) -> Option<f64> {
+        match self {
+            Speed::Normal => Some(1.0),
+            Speed::Double => Some(2.0),
+            Speed::Ten => Some(10.0),
+            Speed::Max => None
+        }
+    }
+}
+
+/// Longest a single call to `SimClock::run` may spend ticking, so rendering and input keep up.
+const FRAME_BUDGET: Duration = Duration::from_millis(12);
+
+/// Decides how many simulation ticks to run each frame, so the simulation runs at a fixed rate
+/// whatever the frame rate is.
+pub struct SimClock {
+    ticks_per_second: u32,
+    speed: Speed,
+    paused: bool,
+    step: bool,
+    // fractional ticks carried over between frames
+    owed: f64,
+    last: Instant
+}
+
+impl SimClock {
+    pub fn new(ticks_per_second: u32) -> Self {
+        Self {
+            ticks_per_second: ticks_per_second.max(1),
+            speed: Speed::Normal,
+            paused: false,
+            step: false,
+            owed: 0.0,
+            last: Instant::now()
+        }
+    }
+
+    pub fn ticks_per_second(&self) -> u32 {
+        self.ticks_per_second
+    }
+
+    pub fn set_ticks_per_second(&mut self, ticks_per_second: u32) {
+        self.ticks_per_second = ticks_per_second.max(1);
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn toggle_paused(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Run exactly one tick on the next call to `run`, even when paused.
+    pub fn step(&mut self) {
+        self.step = true;
+    }
+
+    /// Call `tick` as many times as are due since the last call, and return how many that was.
+    pub fn run(&mut self, mut tick: impl FnMut()) -> u32 {
+        let start = Instant::now();
+        let elapsed = start.duration_since(self.last).as_secs_f64();
+        self.last = start;
+
+        if self.paused {
+            self.owed = 0.0;
+            if self.step {
+                self.step = false;
+                tick();
+                return 1;
+            }
+            return 0;
+        }
+        self.step = false;
+
+        let mut ran = 0;
+        match self.speed.multiplier() {
+            Some(multiplier) => {
+                self.owed += elapsed * self.ticks_per_second as f64 * multiplier;
+                while self.owed >= 1.0 {
+                    tick();
+                    ran += 1;
+                    self.owed -= 1.0;
+                    if start.elapsed() > FRAME_BUDGET {
+                        // can't keep up, drop the backlog rather than fall further behind
+                        self.owed = 0.0;
+                    }
+                }
+            },
+            None => {
+                self.owed = 0.0;
+                while ran == 0 || start.elapsed() < FRAME_BUDGET {
+                    tick();
+                    ran += 1;
+                }
+            }
+        }
+        ran
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticks(clock: &mut SimClock) -> u32 {
+        let mut count = 0;
+        let ran = clock.run(|| count += 1);
+        assert_eq!(ran, count);
+        ran
+    }
+
+    #[test]
+    fn paused_clock_runs_nothing() {
+        let mut clock = SimClock::new(60);
+        clock.set_paused(true);
+        for speed in [Speed::Normal, Speed::Ten, Speed::Max].iter().copied() {
+            clock.set_speed(speed);
+            std::thread::sleep(Duration::from_millis(20));
+            assert_eq!(ticks(&mut clock), 0);
+        }
+    }
+
+    #[test]
+    fn step_runs_one_tick_while_paused() {
+        let mut clock = SimClock::new(60);
+        clock.toggle_paused();
+        clock.step();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(ticks(&mut clock), 1);
+        assert_eq!(ticks(&mut clock), 0);
+        // a step asked for while running is used up by that frame
+        clock.set_paused(false);
+        clock.step();
+        ticks(&mut clock);
+        clock.set_paused(true);
+        assert_eq!(ticks(&mut clock), 0);
+    }
+
+    #[test]
+    fn max_speed_runs_at_least_one_tick() {
+        let mut clock = SimClock::new(1);
+        clock.set_speed(Speed::Max);
+        assert!(ticks(&mut clock) >= 1);
+    }
+}
@@ -1,7 +1,31 @@
+use crate::world::{ScanOrder, Boundary, DEFAULT_LIGHT_BLUR_RADIUS};
+use crate::sun::DayCycle;
+
 #[derive(new)]
 pub(crate) struct DebugInfo {
     #[new(value = "None")]
     pub world_pos: Option::<(usize, usize)>,
     #[new(value = "false")]
-    pub spawning: bool
+    pub spawning: bool,
+    // while recording, the frames recorded so far and whether it is full
+    #[new(value = "None")]
+    pub recorded_frames: Option::<(usize, bool)>,
+    #[new(value = "0")]
+    pub tick: u64,
+    #[new(value = "(0, 0)")]
+    pub awake_chunks: (usize, usize),
+    #[new(value = "ScanOrder::Fixed")]
+    pub scan_order: ScanOrder,
+    #[new(value = "Boundary::Walls")]
+    pub boundary: Boundary,
+    #[new(value = "0")]
+    pub stray_writes: u64,
+    #[new(value = "0.0")]
+    pub sun_angle: f32,
+    #[new(value = "DEFAULT_LIGHT_BLUR_RADIUS")]
+    pub light_blur_radius: usize,
+    #[new(value = "None")]
+    pub day_cycle: Option::<DayCycle>,
+    #[new(value = "None")]
+    pub time_of_day: Option::<f32>
 }
\ No newline at end of file
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Scale an image up by an integer factor, repeating each pixel `scale` times in each direction.
+/// Returns the new width, height and pixels.
+pub fn upscale(width: usize, height: usize, pixels: &[u8], bytes_per_pixel: usize, scale: usize) -> (usize, usize, Vec::<u8>) {
+    let scale = scale.max(1);
+    if width == 0 || bytes_per_pixel == 0 {
+        return (width * scale, height * scale, vec!());
+    }
+    let mut out = Vec::with_capacity(pixels.len() * scale * scale);
+    for row in pixels.chunks(width * bytes_per_pixel).take(height) {
+        let start = out.len();
+        for pixel in row.chunks(bytes_per_pixel) {
+            for _ in 0..scale {
+                out.extend_from_slice(pixel);
+            }
+        }
+        let end = out.len();
+        for _ in 1..scale {
+            out.extend_from_within(start..end);
+        }
+    }
+    (width * scale, height * scale, out)
+}
+
+/// Write an RGBA image as a binary PPM, dropping the alpha channel.
+pub fn write_ppm(w: &mut impl Write, width: usize, height: usize, rgba: &[u8], scale: usize) -> io::Result<()> {
+    let (width, height, rgba) = upscale(width, height, rgba, 4, scale);
+    write!(w, "P6\n{} {}\n255\n", width, height)?;
+    let rgb: Vec::<u8> = rgba.chunks(4).flat_map(|p| p[..3].iter().copied()).collect();
+    w.write_all(&rgb)
+}
+
+/// Write an RGBA image as an RGB PNG.
+///
+/// The image data is stored without compression, which keeps this free of dependencies at the
+/// cost of larger files.
+pub fn write_png(w: &mut impl Write, width: usize, height: usize, rgba: &[u8], scale: usize) -> io::Result<()> {
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "a PNG must be at least 1x1"));
+    }
+    let (width, height, rgba) = upscale(width, height, rgba, 4, scale);
+
+    // every row starts with filter type 0, no filtering
+    let mut raw = Vec::with_capacity((width * 3 + 1) * height);
+    for row in rgba.chunks(width * 4) {
+        raw.push(0);
+        raw.extend(row.chunks(4).flat_map(|p| p[..3].iter().copied()));
+    }
+
+    w.write_all(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a])?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // bit depth 8, colour type 2 (RGB), default compression, filter and no interlace
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(w, b"IHDR", &ihdr)?;
+    write_chunk(w, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(w, b"IEND", &[])
+}
+
+fn write_chunk(w: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let crc = !crc32_update(crc32_update(0xffff_ffff, kind), data);
+    w.write_all(&crc.to_be_bytes())
+}
+
+/// Wrap `data` in a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec::<u8> {
+    const MAX_BLOCK: usize = 0xffff;
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_BLOCK * 5 + 11);
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+/// Write indexed frames as a looping animated GIF. Every frame holds one palette index per pixel
+/// and `delay` is the time each frame is shown for, in hundredths of a second.
+pub fn write_gif(w: &mut impl Write, width: usize, height: usize, palette: &[[u8; 3]], frames: &[Vec::<u8>], scale: usize, delay: u16) -> io::Result<()> {
+    let (scaled_width, scaled_height) = (width * scale.max(1), height * scale.max(1));
+    if scaled_width > 0xffff || scaled_height > 0xffff || palette.len() > 256 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "image too large for a GIF"));
+    }
+
+    w.write_all(b"GIF89a")?;
+    w.write_all(&(scaled_width as u16).to_le_bytes())?;
+    w.write_all(&(scaled_height as u16).to_le_bytes())?;
+    // global colour table of 256 entries, no background colour or aspect ratio
+    w.write_all(&[0xf7, 0, 0])?;
+    for i in 0..256 {
+        w.write_all(palette.get(i).unwrap_or(&[0, 0, 0]))?;
+    }
+
+    // loop forever
+    w.write_all(&[0x21, 0xff, 0x0b])?;
+    w.write_all(b"NETSCAPE2.0")?;
+    w.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+
+    for frame in frames {
+        let (_, _, indices) = upscale(width, height, frame, 1, scale);
+
+        // graphic control extension, for the frame delay
+        w.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+        w.write_all(&delay.to_le_bytes())?;
+        w.write_all(&[0x00, 0x00])?;
+
+        // image descriptor covering the whole screen
+        w.write_all(&[0x2c, 0, 0, 0, 0])?;
+        w.write_all(&(scaled_width as u16).to_le_bytes())?;
+        w.write_all(&(scaled_height as u16).to_le_bytes())?;
+        w.write_all(&[0x00])?;
+
+        w.write_all(&[GIF_MIN_CODE_SIZE])?;
+        for block in lzw_encode(&indices).chunks(255) {
+            w.write_all(&[block.len() as u8])?;
+            w.write_all(block)?;
+        }
+        w.write_all(&[0x00])?;
+    }
+
+    w.write_all(&[0x3b])
+}
+
+const GIF_MIN_CODE_SIZE: u8 = 8;
+
+/// Variable width LZW as used by GIF, with codes packed least significant bit first.
+fn lzw_encode(indices: &[u8]) -> Vec::<u8> {
+    let clear = 1u16 << GIF_MIN_CODE_SIZE;
+    let end = clear + 1;
+
+    let mut out = Vec::new();
+    let (mut bits, mut bit_count) = (0u32, 0u32);
+    let mut emit = |code: u16, size: u32, out: &mut Vec::<u8>| {
+        bits |= (code as u32) << bit_count;
+        bit_count += size;
+        while bit_count >= 8 {
+            out.push(bits as u8);
+            bits >>= 8;
+            bit_count -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = GIF_MIN_CODE_SIZE as u32 + 1;
+    let mut next_code = end + 1;
+    emit(clear, code_size, &mut out);
+
+    let mut pixels = indices.iter();
+    if let Some(first) = pixels.next() {
+        let mut prefix = *first as u16;
+        for &k in pixels {
+            if let Some(&code) = table.get(&(prefix, k)) {
+                prefix = code;
+                continue;
+            }
+            emit(prefix, code_size, &mut out);
+            if next_code < 4096 {
+                table.insert((prefix, k), next_code);
+                next_code += 1;
+                if next_code > (1 << code_size) && code_size < 12 {
+                    code_size += 1;
+                }
+            }
+            else {
+                emit(clear, code_size, &mut out);
+                table.clear();
+                code_size = GIF_MIN_CODE_SIZE as u32 + 1;
+                next_code = end + 1;
+            }
+            prefix = k as u16;
+        }
+        emit(prefix, code_size, &mut out);
+    }
+    emit(end, code_size, &mut out);
+    if bit_count > 0 {
+        out.push(bits as u8);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upscale_repeats_pixels() {
+        let (width, height, pixels) = upscale(2, 1, &[1, 2], 1, 2);
+        assert_eq!((width, height), (4, 2));
+        assert_eq!(pixels, vec![1, 1, 2, 2, 1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn upscale_empty_image() {
+        assert_eq!(upscale(0, 3, &[], 4, 2), (0, 6, vec!()));
+        assert_eq!(upscale(3, 0, &[], 4, 2), (6, 0, vec!()));
+    }
+
+    #[test]
+    fn empty_png_is_an_error() {
+        assert!(write_png(&mut vec!(), 0, 4, &[], 1).is_err());
+        assert!(write_png(&mut vec!(), 1, 1, &[0, 0, 0, 255], 1).is_ok());
+    }
+}
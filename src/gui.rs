@@ -1,7 +1,23 @@
 use pixels::{wgpu, PixelsContext};
 use std::time::Instant;
 
+use crate::clock::{SimClock, Speed};
 use crate::debug::DebugInfo;
+use crate::stats::PopulationHistory;
+use crate::world::{ScanOrder, Boundary, MAX_SUN_ANGLE, MAX_LIGHT_BLUR_RADIUS};
+use crate::sun::{self, DayCycle};
+
+/// Series in the Population window, as (label, cell name).
+const POPULATION_SERIES: [(&str, &str); 8] = [
+    ("Fish", "Fish"),
+    ("Worms", "Worm"),
+    ("Algae", "Algae"),
+    ("Plants", "Plant"),
+    ("Kelp", "Kelp"),
+    ("Bacteria", "BottomFeeder"),
+    ("Decay", "Decay"),
+    ("Nitrogen", "Nitrogen"),
+];
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum SelectedCell {
@@ -13,13 +29,31 @@ pub enum SelectedCell {
     Stone,
     Fizzer,
     KelpSeed,
-    Worm
+    Worm,
+    Lamp
+}
+
+/// What the GUI shows each frame, and the clock it controls.
+pub(crate) struct GuiInput<'a> {
+    pub debug: &'a DebugInfo,
+    pub history: &'a PopulationHistory,
+    pub clock: &'a mut SimClock
 }
 
 pub(crate) struct GuiState {
     pub selected_cell : SelectedCell,
     pub smooth_lighting : bool,
-    pub block_spawn: bool
+    pub block_spawn: bool,
+    pub block_keys: bool,
+    pub save: bool,
+    pub load: bool,
+    pub recording: bool,
+    pub parallel: bool,
+    pub scan_order: ScanOrder,
+    pub boundary: Boundary,
+    pub sun_angle: f32,
+    pub light_blur_radius: usize,
+    pub day_cycle: Option::<DayCycle>
 }
 
 /// Manages all state required for rendering Dear ImGui over `Pixels`.
@@ -30,7 +64,10 @@ pub(crate) struct Gui {
     last_frame: Instant,
     last_cursor: Option<imgui::MouseCursor>,
     selected_cell : SelectedCell,
-    smooth_lighting: bool
+    smooth_lighting: bool,
+    recording: bool,
+    parallel: bool,
+    show_series: [bool; POPULATION_SERIES.len()]
 }
 
 impl Gui {
@@ -44,7 +81,7 @@ impl Gui {
         let mut platform = imgui_winit_support::WinitPlatform::init(&mut imgui);
         platform.attach_window(
             imgui.io_mut(),
-            &window,
+            window,
             imgui_winit_support::HiDpiMode::Default,
         );
 
@@ -73,7 +110,7 @@ impl Gui {
         let device = pixels.device();
         let queue = pixels.queue();
         let texture_format = wgpu::TextureFormat::Bgra8UnormSrgb;
-        let renderer = imgui_wgpu::Renderer::new(&mut imgui, &device, &queue, texture_format);
+        let renderer = imgui_wgpu::Renderer::new(&mut imgui, device, queue, texture_format);
 
         // Return GUI context
         Self {
@@ -83,7 +120,10 @@ impl Gui {
             last_frame: Instant::now(),
             last_cursor: None,
             selected_cell: SelectedCell::Sand,
-            smooth_lighting: false
+            smooth_lighting: false,
+            recording: false,
+            parallel: false,
+            show_series: [true; POPULATION_SERIES.len()]
         }
     }
 
@@ -105,8 +145,10 @@ impl Gui {
         encoder: &mut wgpu::CommandEncoder,
         render_target: &wgpu::TextureView,
         context: &PixelsContext,
-        debug: &DebugInfo
+        input: GuiInput
     ) -> GuiState {
+        let GuiInput { debug, history, clock } = input;
+
         // Start a new Dear ImGui frame and update the cursor
         let ui = self.imgui.frame();
 
@@ -118,7 +160,18 @@ impl Gui {
 
         let mut selected_cell = self.selected_cell;
         let mut smooth_lighting = self.smooth_lighting;
+        let mut recording = self.recording;
+        let mut parallel = self.parallel;
+        // the world owns these, so they follow loads
+        let mut scan_order = debug.scan_order;
+        let mut wrap = debug.boundary == Boundary::WrapX;
+        let mut sun_angle = debug.sun_angle;
+        let mut light_blur_radius = debug.light_blur_radius as u32;
+        let mut day_cycle = debug.day_cycle;
+        let mut show_series = self.show_series;
         let mut block_spawn = false;
+        let mut save = false;
+        let mut load = false;
         imgui::Window::new(imgui::im_str!("Debug"))
             .position([50.0, 50.0], imgui::Condition::FirstUseEver)
             .size([200.0, 200.0], imgui::Condition::FirstUseEver)
@@ -127,10 +180,58 @@ impl Gui {
                 ui.text(imgui::im_str!("RMB = Clear"));     
                 ui.text(format!("{:.2} FPS", ui.io().framerate));                      
                 ui.text(format!("Spawning: {}", debug.spawning));
+                ui.text(format!("Awake chunks: {}/{}", debug.awake_chunks.0, debug.awake_chunks.1));
+                ui.text(format!("Stray writes: {}", debug.stray_writes));
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Cells written outside the world and dropped.");
+                }
                 ui.text(format!(
                     "World Position: ({:.1},{:.1})", debug.world_pos.unwrap_or_default().0, debug.world_pos.unwrap_or_default().1
                 ));
                 block_spawn |= ui.checkbox(imgui::im_str!("Smooth Lighting"), &mut smooth_lighting); 
+                block_spawn |= imgui::Slider::new(imgui::im_str!("Blur"), 0..=MAX_LIGHT_BLUR_RADIUS as u32)
+                    .display_format(imgui::im_str!("%d cells"))
+                    .build(&ui, &mut light_blur_radius);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("How far either side of each cell smooth lighting averages over.");
+                }
+                block_spawn |= imgui::Slider::new(imgui::im_str!("Sun"), -MAX_SUN_ANGLE..=MAX_SUN_ANGLE)
+                    .display_format(imgui::im_str!("%.0f deg"))
+                    .build(&ui, &mut sun_angle);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Angle of the sunlight. Stones cast their shadows away from the sun.");
+                }
+                block_spawn |= ui.checkbox(imgui::im_str!("Parallel"), &mut parallel);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Update the world on every core.");
+                }
+                ui.text(imgui::im_str!("Scan order:"));
+                let mut scan_button = |name, value, tooltip| {
+                    block_spawn |= ui.radio_button(name, &mut scan_order, value);
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(tooltip);
+                    }
+                };
+                scan_button(imgui::im_str!("Fixed"), ScanOrder::Fixed, "Left to right.");
+                ui.same_line(0.0);
+                scan_button(imgui::im_str!("Alternate"), ScanOrder::Alternating, "Switch sides every tick.");
+                ui.same_line(0.0);
+                scan_button(imgui::im_str!("Shuffle"), ScanOrder::Shuffled, "Random order every tick.");
+                block_spawn |= ui.checkbox(imgui::im_str!("Wrap Sides"), &mut wrap);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Cells leaving one side come back on the other.");
+                }
+                save = ui.button(imgui::im_str!("Save"), [0.0, 0.0]);
+                ui.same_line(0.0);
+                load = ui.button(imgui::im_str!("Load"), [0.0, 0.0]);
+                block_spawn |= ui.checkbox(imgui::im_str!("Record"), &mut recording);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Records a GIF as the tank ticks, until unticked or full.");
+                }
+                if let Some((frames, full)) = debug.recorded_frames {
+                    ui.same_line(0.0);
+                    ui.text(format!("{} frames{}", frames, if full { " (full)" } else { "" }));
+                }
                 block_spawn |= ui.is_window_hovered();        
             });
 
@@ -155,9 +256,97 @@ impl Gui {
                 cell_button(imgui::im_str!("Fizzer"), SelectedCell::Fizzer, "Makes bubbles.");
                 cell_button(imgui::im_str!("Kelp"), SelectedCell::KelpSeed, "Grows. Dies without light.");       
                 cell_button(imgui::im_str!("Worm"), SelectedCell::Worm, "Eats algae and waste. Grows.");           
+                cell_button(imgui::im_str!("Lamp"), SelectedCell::Lamp, "Lights up the water around it. Stone blocks its light.");
                 block_spawn |= ui.is_window_hovered(); 
             });
 
+        imgui::Window::new(imgui::im_str!("Clock"))
+            .position([260.0, 50.0], imgui::Condition::FirstUseEver)
+            .size([200.0, 160.0], imgui::Condition::FirstUseEver)
+            .build(&ui, || {
+                ui.text(format!("Tick: {}", debug.tick));
+                match debug.time_of_day {
+                    Some(phase) => ui.text(format!("Time: {}", sun::clock_time(phase))),
+                    None => ui.text(imgui::im_str!("Time: always noon"))
+                }
+                let mut paused = clock.paused();
+                if ui.checkbox(imgui::im_str!("Paused"), &mut paused) {
+                    clock.set_paused(paused);
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Space");
+                }
+                ui.same_line(0.0);
+                if ui.button(imgui::im_str!("Step"), [0.0, 0.0]) {
+                    clock.step();
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Period");
+                }
+
+                let mut speed = clock.speed();
+                let mut speed_button = |name, value, key| {
+                    ui.radio_button(name, &mut speed, value);
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(key);
+                    }
+                };
+                speed_button(imgui::im_str!("x1"), Speed::Normal, "1");
+                ui.same_line(0.0);
+                speed_button(imgui::im_str!("x2"), Speed::Double, "2");
+                ui.same_line(0.0);
+                speed_button(imgui::im_str!("x10"), Speed::Ten, "3");
+                ui.same_line(0.0);
+                speed_button(imgui::im_str!("Max"), Speed::Max, "4");
+                clock.set_speed(speed);
+
+                let mut ticks_per_second = clock.ticks_per_second() as i32;
+                if ui.input_int(imgui::im_str!("Ticks/s"), &mut ticks_per_second).build() {
+                    clock.set_ticks_per_second(ticks_per_second.max(1) as u32);
+                }
+
+                let mut day_length = day_cycle.map_or(0, |c| c.length) as i32;
+                let mut sweep = matches!(day_cycle, Some(DayCycle { sweep: true, .. }));
+                let mut changed = ui.input_int(imgui::im_str!("Day length"), &mut day_length).step(600).build();
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Ticks from one midnight to the next. 0 for endless noon.");
+                }
+                changed |= ui.checkbox(imgui::im_str!("Sweep Sun"), &mut sweep);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Move the sun across the sky through the day.");
+                }
+                if changed {
+                    day_cycle = Some(DayCycle { length: day_length.max(0) as u64, sweep }).filter(|c| c.length > 0);
+                }
+                block_spawn |= ui.is_window_hovered();
+            });
+
+        imgui::Window::new(imgui::im_str!("Population"))
+            .position([760.0, 50.0], imgui::Condition::FirstUseEver)
+            .size([220.0, 500.0], imgui::Condition::FirstUseEver)
+            .build(&ui, || {
+                for (i, (label, _)) in POPULATION_SERIES.iter().enumerate() {
+                    block_spawn |= ui.checkbox(&imgui::ImString::new(*label), &mut show_series[i]);
+                    if i % 2 == 0 {
+                        ui.same_line(110.0);
+                    }
+                }
+                for (i, (label, name)) in POPULATION_SERIES.iter().enumerate() {
+                    if !show_series[i] {
+                        continue;
+                    }
+                    let values: Vec::<f32> = history.iter().map(|p| p.count(name) as f32).collect();
+                    let id = imgui::ImString::new(format!("##{}", label));
+                    let overlay = imgui::ImString::new(format!("{}: {}", label, values.last().copied().unwrap_or(0.0)));
+                    ui.plot_lines(&id, &values)
+                        .overlay_text(&overlay)
+                        .scale_min(0.0)
+                        .graph_size([0.0, 40.0])
+                        .build();
+                }
+                block_spawn |= ui.is_window_hovered();
+            });
+
         // Render Dear ImGui with WGPU
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
@@ -171,13 +360,27 @@ impl Gui {
             depth_stencil_attachment: None,
         });
 
+        let block_keys = ui.io().want_capture_keyboard;
         self.selected_cell = selected_cell;
         self.smooth_lighting = smooth_lighting;
+        self.recording = recording;
+        self.parallel = parallel;
+        self.show_series = show_series;
         let _ = self.renderer.render(ui.render(), &context.queue, &context.device, &mut rpass);
         GuiState {
             selected_cell,
             smooth_lighting,
-            block_spawn
+            block_spawn,
+            block_keys,
+            save,
+            load,
+            recording,
+            parallel,
+            scan_order,
+            boundary: if wrap { Boundary::WrapX } else { Boundary::Walls },
+            sun_angle,
+            light_blur_radius: light_blur_radius as usize,
+            day_cycle
         }
     }
 
@@ -0,0 +1,370 @@
+use std::cmp;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::time::Instant;
+use log::LevelFilter;
+
+use crate::cell::{Cell, Organism};
+use crate::export;
+use crate::logger;
+use crate::registry;
+use crate::sun::{self, DayCycle};
+use crate::record::Recorder;
+use crate::stats::Population;
+use crate::world::{World, Schedule, ScanOrder, Boundary, OutOfBounds, DEFAULT_WORLD_WIDTH, DEFAULT_WORLD_HEIGHT};
+
+const USAGE: &str = "usage: fishtank-headless [--ticks N] [--seed N] [--size WIDTHxHEIGHT] [--scene empty|demo|FILE] [--load FILE] [--save FILE] [--screenshot FILE.png|FILE.ppm] [--scale N] [--smooth-lighting] [--blur-radius N] [--record FILE.gif|PREFIX] [--record-every N] [--stats FILE.csv] [--organisms FILE.csv] [--threads N] [--scan fixed|alternating|shuffled] [--wrap] [--sun-angle DEGREES] [--day-length TICKS] [--sweep-sun] [--out-of-bounds clamp|drop|log]";
+
+struct Options {
+    ticks: u64,
+    seed: Option<u64>,
+    width: usize,
+    height: usize,
+    scene: String,
+    load: Option<String>,
+    save: Option<String>,
+    screenshot: Option<String>,
+    scale: usize,
+    smooth_lighting: bool,
+    blur_radius: Option<usize>,
+    record: Option<String>,
+    record_every: u32,
+    stats: Option<String>,
+    organisms: Option<String>,
+    threads: Option<usize>,
+    scan: Option<ScanOrder>,
+    wrap: bool,
+    sun_angle: Option<f32>,
+    day_length: Option<u64>,
+    sweep_sun: bool,
+    out_of_bounds: Option<OutOfBounds>
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            ticks: 10_000,
+            seed: None,
+            width: DEFAULT_WORLD_WIDTH,
+            height: DEFAULT_WORLD_HEIGHT,
+            scene: "demo".to_string(),
+            load: None,
+            save: None,
+            screenshot: None,
+            scale: 1,
+            smooth_lighting: false,
+            blur_radius: None,
+            record: None,
+            record_every: 10,
+            stats: None,
+            organisms: None,
+            threads: None,
+            scan: None,
+            wrap: false,
+            sun_angle: None,
+            day_length: None,
+            sweep_sun: false,
+            out_of_bounds: None
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--ticks" => {
+                    options.ticks = value()?.parse().map_err(|_| "--ticks must be a positive integer")?;
+                },
+                "--seed" => {
+                    options.seed = Some(value()?.parse().map_err(|_| "--seed must be a positive integer")?);
+                },
+                "--size" => {
+                    let v = value()?;
+                    let mut parts = v.split('x').map(|p| p.parse::<usize>());
+                    match (parts.next(), parts.next(), parts.next()) {
+                        (Some(Ok(w)), Some(Ok(h)), None) if w > 0 && h > 0 => {
+                            options.width = w;
+                            options.height = h;
+                        },
+                        _ => return Err("--size must look like 256x144".to_string())
+                    }
+                },
+                "--scene" => {
+                    options.scene = value()?;
+                },
+                "--load" => {
+                    options.load = Some(value()?);
+                },
+                "--save" => {
+                    options.save = Some(value()?);
+                },
+                "--screenshot" => {
+                    options.screenshot = Some(value()?);
+                },
+                "--scale" => {
+                    options.scale = value()?.parse().map_err(|_| "--scale must be a positive integer")?;
+                },
+                "--smooth-lighting" => {
+                    options.smooth_lighting = true;
+                },
+                "--blur-radius" => {
+                    options.blur_radius = Some(value()?.parse().map_err(|_| "--blur-radius must be a number of cells")?);
+                },
+                "--record" => {
+                    options.record = Some(value()?);
+                },
+                "--record-every" => {
+                    options.record_every = value()?.parse().map_err(|_| "--record-every must be a positive integer")?;
+                },
+                "--stats" => {
+                    options.stats = Some(value()?);
+                },
+                "--organisms" => {
+                    options.organisms = Some(value()?);
+                },
+                "--threads" => {
+                    options.threads = Some(value()?.parse().map_err(|_| "--threads must be a positive integer")?);
+                },
+                "--out-of-bounds" => {
+                    options.out_of_bounds = Some(match value()?.as_str() {
+                        "clamp" => OutOfBounds::Clamp,
+                        "drop" => OutOfBounds::Drop,
+                        "log" => OutOfBounds::Log,
+                        _ => return Err("--out-of-bounds must be clamp, drop or log".to_string())
+                    });
+                },
+                "--wrap" => {
+                    options.wrap = true;
+                },
+                "--day-length" => {
+                    options.day_length = Some(value()?.parse().map_err(|_| "--day-length must be a number of ticks")?);
+                },
+                "--sweep-sun" => {
+                    options.sweep_sun = true;
+                },
+                "--sun-angle" => {
+                    options.sun_angle = Some(value()?.parse().map_err(|_| "--sun-angle must be a number of degrees")?);
+                },
+                "--scan" => {
+                    options.scan = Some(match value()?.as_str() {
+                        "fixed" => ScanOrder::Fixed,
+                        "alternating" => ScanOrder::Alternating,
+                        "shuffled" => ScanOrder::Shuffled,
+                        _ => return Err("--scan must be fixed, alternating or shuffled".to_string())
+                    });
+                },
+                _ => return Err(format!("unknown argument {}", arg))
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Spawn a small self-sustaining tank: a sand bed with some rocks, plants, and a few animals.
+fn spawn_demo(world: &mut World) {
+    let (w, h) = (world.width(), world.height());
+    let bed = cmp::max(h / 8, 1);
+    let floor = h.saturating_sub(bed + 1);
+
+    for x in 0..w {
+        for y in (floor + 1)..h {
+            world.spawn((x, y), Cell::Sand);
+        }
+    }
+
+    for x in (w / 5)..(w / 5 + w / 16) {
+        world.spawn((x, floor), Cell::Stone);
+        world.spawn((x, floor.saturating_sub(1)), Cell::Stone);
+    }
+
+    for i in 0..8 {
+        let x = (w * (2 * i + 1)) / 16;
+        world.spawn((x, floor), if i % 2 == 0 { Cell::Seed } else { Cell::KelpSeed });
+        world.spawn((x + 1, floor), Cell::BottomFeeder);
+        world.spawn_organism((x, h / 3), Organism::new_algae());
+    }
+
+    world.spawn_organism((w / 4, h / 4), Organism::new_fish());
+    world.spawn_organism((3 * w / 4, h / 3), Organism::new_fish());
+    world.spawn_organism((w / 2, h / 2), Organism::new_worm());
+    world.spawn((w.saturating_sub(2), floor), Cell::Fizzer);
+}
+
+/// Render the world to `path`, as a PPM if it ends in `.ppm` and a PNG otherwise.
+fn write_image(world: &mut World, path: &str, scale: usize, smooth_lighting: bool) -> io::Result<()> {
+    let rgba = world.render_to_rgba(smooth_lighting);
+    let mut w = BufWriter::new(File::create(path)?);
+    if path.ends_with(".ppm") {
+        export::write_ppm(&mut w, world.width(), world.height(), &rgba, scale)?;
+    }
+    else {
+        export::write_png(&mut w, world.width(), world.height(), &rgba, scale)?;
+    }
+    w.flush()
+}
+
+/// Run the simulation without a window and print a summary of the final tank.
+pub fn run_headless() -> Result<(), String> {
+    let options = Options::parse(env::args().skip(1)).map_err(|e| format!("{}\n{}", e, USAGE))?;
+
+    let mut world = if let Some(path) = &options.load {
+        // a save carries its own size, contents and random state
+        File::open(path)
+            .and_then(|f| World::load(&mut BufReader::new(f)))
+            .map_err(|e| format!("loading {} failed: {}", path, e))?
+    }
+    else {
+        let mut world = match options.scene.as_str() {
+            "empty" | "demo" => World::new(options.width, options.height),
+            // anything else is an ASCII scene file, which sets the size itself
+            path => {
+                let text = fs::read_to_string(path).map_err(|e| format!("reading {} failed: {}", path, e))?;
+                World::from_ascii(&text).map_err(|e| format!("{}: {}", path, e))?
+            }
+        };
+        if let Some(seed) = options.seed {
+            world.reseed(seed);
+        }
+        if options.scene == "demo" {
+            spawn_demo(&mut world);
+        }
+        world
+    };
+
+    // a loaded world keeps the order it was saved with unless told otherwise
+    if let Some(scan) = options.scan {
+        world.set_scan_order(scan);
+    }
+    if options.wrap {
+        world.set_boundary(Boundary::WrapX);
+    }
+    if let Some(degrees) = options.sun_angle {
+        world.set_sun_angle(degrees);
+    }
+    if let Some(radius) = options.blur_radius {
+        world.set_light_blur_radius(radius);
+    }
+    if options.day_length.is_some() || options.sweep_sun {
+        // sweeping a loaded world's sun keeps the length of its day
+        let length = options.day_length.or_else(|| world.day_cycle().map(|c| c.length));
+        match length {
+            Some(length) => world.set_day_cycle(Some(DayCycle { length, sweep: options.sweep_sun })),
+            None => return Err(format!("--sweep-sun needs a --day-length\n{}", USAGE))
+        }
+    }
+    if let Some(policy) = options.out_of_bounds {
+        world.set_out_of_bounds(policy);
+        if policy == OutOfBounds::Log {
+            logger::log_to_stderr(LevelFilter::Debug);
+        }
+    }
+    if let Some(threads) = options.threads {
+        world.set_schedule(Schedule::Checkerboard { threads });
+    }
+    // populations are streamed to --stats instead of being kept
+    world.history_mut().set_capacity(0);
+    let mut stats = match &options.stats {
+        Some(path) => Some(
+            File::create(path)
+                .and_then(|f| {
+                    let mut w = BufWriter::new(f);
+                    Population::write_csv_header(&mut w)?;
+                    Ok(w)
+                })
+                .map_err(|e| format!("writing {} failed: {}", path, e))?
+        ),
+        None => None
+    };
+    let mut recorder = options.record.as_ref().map(|_| Recorder::new(world.width(), world.height(), options.record_every));
+
+    let start = Instant::now();
+    for _ in 0..options.ticks {
+        world.update();
+        if let (Some(path), Some(w)) = (&options.stats, &mut stats) {
+            world.census().write_csv_row(w).map_err(|e| format!("writing {} failed: {}", path, e))?;
+        }
+        if let Some(recorder) = &mut recorder {
+            if recorder.due() {
+                let rgba = world.render_to_rgba(options.smooth_lighting);
+                recorder.frame(&rgba);
+            }
+            else {
+                recorder.skip();
+            }
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!(
+        "{} ticks in {:.2}s ({:.1} ticks/s)",
+        options.ticks, elapsed, options.ticks as f64 / elapsed.max(f64::EPSILON)
+    );
+    if let Some(phase) = world.time_of_day() {
+        println!("time of day {}", sun::clock_time(phase));
+    }
+    let (awake, chunks) = world.awake_chunks();
+    println!("{} of {} chunks awake", awake, chunks);
+    if world.stray_writes() > 0 {
+        println!("{} cells written outside the world were dropped", world.stray_writes());
+    }
+    let population = world.census();
+    for (name, count) in population.counts().filter(|(_, count)| *count > 0) {
+        println!("{:<14}{:>8}", name, count);
+    }
+    println!("{:<14}{:>8}", "Energy", population.energy());
+    if let Some(oldest) = world.organisms().max_by_key(|o| (o.age, cmp::Reverse(o.id))) {
+        println!("oldest organism is {} {}, {} ticks old", oldest.species, oldest.id, oldest.age);
+    }
+
+    if let (Some(path), Some(w)) = (&options.stats, &mut stats) {
+        w.flush().map_err(|e| format!("writing {} failed: {}", path, e))?;
+    }
+
+    if let Some(path) = &options.organisms {
+        File::create(path)
+            .and_then(|f| {
+                let mut w = BufWriter::new(f);
+                registry::write_organisms_csv(world.organisms(), &mut w)?;
+                w.flush()
+            })
+            .map_err(|e| format!("writing {} failed: {}", path, e))?;
+    }
+
+    if let Some(path) = &options.screenshot {
+        write_image(&mut world, path, options.scale, options.smooth_lighting)
+            .map_err(|e| format!("writing {} failed: {}", path, e))?;
+    }
+
+    if let (Some(path), Some(recorder)) = (&options.record, &recorder) {
+        recorder.write_to(path, options.scale, 4)
+            .map_err(|e| format!("writing {} failed: {}", path, e))?;
+        println!("recorded {} frames to {}", recorder.len(), path);
+    }
+
+    if let Some(path) = &options.save {
+        File::create(path)
+            .and_then(|f| {
+                let mut w = BufWriter::new(f);
+                world.save(&mut w)?;
+                w.flush()
+            })
+            .map_err(|e| format!("saving {} failed: {}", path, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demo_fits_tiny_tanks() {
+        // nothing in the demo is placed left of or above the tank, however small it is
+        for (w, h) in [(1, 1), (2, 1), (1, 2), (3, 3), (40, 1), (1, 40)].iter().copied() {
+            let mut world = World::new(w, h);
+            spawn_demo(&mut world);
+            for _ in 0..10 {
+                world.update();
+            }
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Prints log messages to stderr.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        eprintln!("{}: {}", record.level(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// Print log messages up to `level` to stderr. Does nothing if a logger is already installed.
+pub fn log_to_stderr(level: LevelFilter) {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::export;
+
+/// Captures every Nth frame of a run so it can be written out as an animated GIF or a sequence of
+/// numbered PNGs.
+///
+/// Frames are stored as indices into a shared palette. The world only ever draws a couple of
+/// hundred distinct colours; if a frame brings more than 256, the extra ones are matched to the
+/// nearest colour already in the palette.
+pub struct Recorder {
+    width: usize,
+    height: usize,
+    every: u32,
+    seen: u32,
+    limit: Option::<usize>,
+    palette: Vec::<[u8; 3]>,
+    lookup: HashMap<[u8; 3], u8>,
+    frames: Vec::<Vec::<u8>>
+}
+
+impl Recorder {
+    /// Record frames of a `width` by `height` world, keeping one in every `every`.
+    pub fn new(width: usize, height: usize, every: u32) -> Self {
+        Self {
+            width,
+            height,
+            every: every.max(1),
+            seen: 0,
+            limit: None,
+            palette: vec!(),
+            lookup: HashMap::new(),
+            frames: vec!()
+        }
+    }
+
+    /// Whether the next frame passed to `frame` will be kept. Lets callers skip rendering the others.
+    pub fn due(&self) -> bool {
+        self.seen.is_multiple_of(self.every)
+    }
+
+    /// Count a frame without looking at it. Use when `due` is false and the frame wasn't rendered.
+    pub fn skip(&mut self) {
+        self.seen += 1;
+    }
+
+    /// Offer an RGBA frame, as drawn by `World::draw`.
+    pub fn frame(&mut self, rgba: &[u8]) {
+        let keep = self.due();
+        self.seen += 1;
+        if !keep || self.is_full() {
+            return;
+        }
+
+        let mut indices = Vec::with_capacity(self.width * self.height);
+        for pixel in rgba.chunks(4).take(self.width * self.height) {
+            indices.push(self.index_of([pixel[0], pixel[1], pixel[2]]));
+        }
+        self.frames.push(indices);
+    }
+
+    /// Number of frames kept so far.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Keep at most `frames` frames, and ignore any offered after that. There is no limit by default.
+    pub fn set_limit(&mut self, frames: usize) {
+        self.limit = Some(frames);
+        self.frames.truncate(frames);
+    }
+
+    /// Whether as many frames have been kept as the limit allows.
+    pub fn is_full(&self) -> bool {
+        self.limit.is_some_and(|limit| self.frames.len() >= limit)
+    }
+
+    fn index_of(&mut self, color: [u8; 3]) -> u8 {
+        if let Some(i) = self.lookup.get(&color) {
+            return *i;
+        }
+
+        let i = if self.palette.len() < 256 {
+            self.palette.push(color);
+            (self.palette.len() - 1) as u8
+        }
+        else {
+            let distance = |p: &[u8; 3]| -> i32 {
+                (0..3).map(|c| (p[c] as i32 - color[c] as i32).pow(2)).sum()
+            };
+            (0..self.palette.len()).min_by_key(|i| distance(&self.palette[*i])).unwrap() as u8
+        };
+        self.lookup.insert(color, i);
+        i
+    }
+
+    /// Write the kept frames as a looping GIF, each frame shown for `delay` hundredths of a second.
+    pub fn write_gif(&self, w: &mut impl Write, scale: usize, delay: u16) -> io::Result<()> {
+        export::write_gif(w, self.width, self.height, &self.palette, &self.frames, scale, delay)
+    }
+
+    /// Write the kept frames as PNGs named `<prefix>00000.png`, `<prefix>00001.png` and so on.
+    pub fn write_sequence(&self, prefix: &str, scale: usize) -> io::Result<()> {
+        for (n, frame) in self.frames.iter().enumerate() {
+            let mut rgba = Vec::with_capacity(frame.len() * 4);
+            for i in frame {
+                let [r, g, b] = self.palette[*i as usize];
+                rgba.extend_from_slice(&[r, g, b, 0xff]);
+            }
+            let mut w = BufWriter::new(File::create(format!("{}{:05}.png", prefix, n))?);
+            export::write_png(&mut w, self.width, self.height, &rgba, scale)?;
+            w.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write a GIF if `path` ends in `.gif`, otherwise a PNG sequence using `path` as the prefix.
+    pub fn write_to(&self, path: &str, scale: usize, delay: u16) -> io::Result<()> {
+        if path.ends_with(".gif") {
+            let mut w = BufWriter::new(File::create(path)?);
+            self.write_gif(&mut w, scale, delay)?;
+            w.flush()
+        }
+        else {
+            self.write_sequence(path, scale)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_every_nth_frame_up_to_the_limit() {
+        let mut recorder = Recorder::new(1, 1, 3);
+        recorder.set_limit(2);
+        let mut kept = vec!();
+        for n in 0..10u8 {
+            if recorder.due() {
+                kept.push(n);
+            }
+            recorder.frame(&[n, n, n, 255]);
+        }
+        assert_eq!(kept, vec![0, 3, 6, 9]);
+        assert_eq!(recorder.len(), 2);
+        assert!(recorder.is_full());
+        assert_eq!(recorder.frames, vec![vec![0], vec![1]]);
+    }
+}
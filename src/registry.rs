@@ -0,0 +1,43 @@
+use std::fmt;
+use std::io::{self, Write};
+
+/// Identifies one organism for its whole life. IDs are never reused within a world and are kept
+/// by saves, so an individual fish can be followed from spawn to death.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OrganismId(pub u64);
+
+impl fmt::Display for OrganismId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Who an organism in the arena is, stored alongside its state.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Life {
+    pub id: OrganismId,
+    // ticks the organism has been in the world, counting the one it was born in
+    pub age: u64
+}
+
+/// A living organism as listed by `World::organisms`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrganismInfo {
+    pub id: OrganismId,
+    /// Name of the organism's head cell, as in `CELL_NAMES`.
+    pub species: &'static str,
+    /// Where its head cell is.
+    pub position: (usize, usize),
+    pub energy: i32,
+    /// Number of ticks it has lived through, counting the one it was born in.
+    pub age: u64
+}
+
+/// Write organisms as CSV, one row each.
+pub fn write_organisms_csv(organisms: impl Iterator<Item = OrganismInfo>, w: &mut impl Write) -> io::Result<()> {
+    writeln!(w, "id,species,x,y,energy,age")?;
+    for o in organisms {
+        writeln!(w, "{},{},{},{},{},{}", o.id.0, o.species, o.position.0, o.position.1, o.energy, o.age)?;
+    }
+    Ok(())
+}
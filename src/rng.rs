@@ -0,0 +1,91 @@
+use rand::{RngCore, SeedableRng, Error};
+use rand_chacha::ChaCha12Rng;
+
+/// The random number generator a `World` simulates with. It gives the same numbers as `StdRng`
+/// from the same seed, and remembers its seed so it can be saved part way through its stream.
+#[derive(Clone, Debug)]
+pub struct WorldRng {
+    seed: [u8; 32],
+    inner: ChaCha12Rng
+}
+
+impl WorldRng {
+    /// The seed it was created from.
+    pub(crate) fn seed(&self) -> [u8; 32] {
+        self.seed
+    }
+
+    /// How many 32-bit words it has given out since it was created.
+    pub(crate) fn word_pos(&self) -> u128 {
+        self.inner.get_word_pos()
+    }
+
+    /// Recreate a generator saved as its seed and word position.
+    pub(crate) fn resume(seed: [u8; 32], word_pos: u128) -> Self {
+        let mut rng = Self::from_seed(seed);
+        rng.inner.set_word_pos(word_pos);
+        rng
+    }
+}
+
+impl SeedableRng for WorldRng {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self {
+            seed,
+            inner: ChaCha12Rng::from_seed(seed)
+        }
+    }
+}
+
+impl RngCore for WorldRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.inner.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.inner.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.inner.fill_bytes(dest)
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.inner.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::Rng;
+
+    #[test]
+    fn same_numbers_as_std_rng() {
+        let mut a = WorldRng::seed_from_u64(42);
+        let mut b = StdRng::seed_from_u64(42);
+        for _ in 0..1000 {
+            assert_eq!(a.gen::<u64>(), b.gen::<u64>());
+            assert_eq!(a.gen_range(0..7), b.gen_range(0..7));
+        }
+    }
+
+    #[test]
+    fn resumes_where_it_was() {
+        let mut rng = WorldRng::seed_from_u64(9);
+        for _ in 0..37 {
+            rng.gen::<u32>();
+        }
+        let mut resumed = WorldRng::resume(rng.seed(), rng.word_pos());
+        for _ in 0..100 {
+            assert_eq!(rng.gen::<u64>(), resumed.gen::<u64>());
+        }
+    }
+}
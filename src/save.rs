@@ -0,0 +1,82 @@
+use std::io::{self, Read, Write};
+
+/// First bytes of every save file.
+pub const MAGIC: &[u8; 4] = b"FTNK";
+/// Bumped whenever the layout of a save changes.
+pub const VERSION: u32 = 1;
+
+pub fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub fn write_u8(w: &mut impl Write, v: u8) -> io::Result<()> {
+    w.write_all(&[v])
+}
+
+pub fn write_u32(w: &mut impl Write, v: u32) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+pub fn write_i32(w: &mut impl Write, v: i32) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+pub fn write_u64(w: &mut impl Write, v: u64) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+pub fn write_u128(w: &mut impl Write, v: u128) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+pub fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut b = [0u8; 1];
+    r.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+pub fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+pub fn read_i32(r: &mut impl Read) -> io::Result<i32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(i32::from_le_bytes(b))
+}
+
+pub fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+pub fn read_u128(r: &mut impl Read) -> io::Result<u128> {
+    let mut b = [0u8; 16];
+    r.read_exact(&mut b)?;
+    Ok(u128::from_le_bytes(b))
+}
+
+pub fn write_offsets(w: &mut impl Write, offsets: &[(i32, i32)]) -> io::Result<()> {
+    write_u32(w, offsets.len() as u32)?;
+    for (x, y) in offsets {
+        write_i32(w, *x)?;
+        write_i32(w, *y)?;
+    }
+    Ok(())
+}
+
+pub fn read_offsets(r: &mut impl Read) -> io::Result<Vec::<(i32, i32)>> {
+    let len = read_u32(r)?;
+    // a body longer than any world could hold means the file is corrupt, don't try to allocate it
+    if len > 1 << 16 {
+        return Err(invalid("organism body too long"));
+    }
+    let mut offsets = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        offsets.push((read_i32(r)?, read_i32(r)?));
+    }
+    Ok(offsets)
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+use crate::cell::{Cell, Organism, CELL_NAMES};
+
+/// A census of the world after one tick.
+///
+/// Organisms are counted by their head cell, so the `Fish` and `Worm` counts are the number of
+/// individuals and their bodies are counted separately as `FishBody` and `WormBody`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Population {
+    pub tick: u64,
+    counts: [usize; CELL_NAMES.len()],
+    energy: i64
+}
+
+impl Population {
+    pub(crate) fn count_cells<'a>(tick: u64, cells: impl Iterator<Item = &'a Cell>, organisms: impl Iterator<Item = &'a Organism>) -> Self {
+        let mut population = Self {
+            tick,
+            counts: [0; CELL_NAMES.len()],
+            energy: 0
+        };
+        for cell in cells {
+            population.counts[cell.kind()] += 1;
+        }
+        population.energy = organisms.map(|o| o.energy() as i64).sum();
+        population
+    }
+
+    /// Number of cells of the variant called `name`, see `CELL_NAMES`.
+    pub fn count(&self, name: &str) -> usize {
+        CELL_NAMES.iter().position(|n| *n == name).map_or(0, |i| self.counts[i])
+    }
+
+    /// Every variant's name with its count, in declaration order.
+    pub fn counts(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        CELL_NAMES.iter().copied().zip(self.counts.iter().copied())
+    }
+
+    /// Total energy held by every fish, worm, plant, kelp and algae.
+    pub fn energy(&self) -> i64 {
+        self.energy
+    }
+
+    /// Write the header of a CSV with a column for the tick, every variant and the total energy.
+    pub fn write_csv_header(w: &mut impl Write) -> io::Result<()> {
+        write!(w, "tick")?;
+        for name in CELL_NAMES.iter() {
+            write!(w, ",{}", name)?;
+        }
+        writeln!(w, ",Energy")
+    }
+
+    /// Write this census as a row of the CSV `write_csv_header` starts.
+    pub fn write_csv_row(&self, w: &mut impl Write) -> io::Result<()> {
+        write!(w, "{}", self.tick)?;
+        for count in self.counts.iter() {
+            write!(w, ",{}", count)?;
+        }
+        writeln!(w, ",{}", self.energy)
+    }
+}
+
+/// The most recent censuses, oldest first. A capacity of 0 keeps none, and `World::update` doesn't
+/// take a census at all.
+pub struct PopulationHistory {
+    capacity: usize,
+    samples: VecDeque<Population>
+}
+
+impl PopulationHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: VecDeque::with_capacity(capacity)
+        }
+    }
+
+    pub(crate) fn push(&mut self, population: Population) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(population);
+    }
+
+    /// Keep at most `capacity` samples, dropping the oldest.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.samples.len() > capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn latest(&self) -> Option<&Population> {
+        self.samples.back()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Population> {
+        self.samples.iter()
+    }
+
+    /// Write the history as CSV, one row per tick with a column for every variant and the total energy.
+    pub fn write_csv(&self, w: &mut impl Write) -> io::Result<()> {
+        Population::write_csv_header(w)?;
+        for population in &self.samples {
+            population.write_csv_row(w)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn census(tick: u64, fish: usize) -> Population {
+        let handle = crate::arena::Arena::new().insert(());
+        let mut cells = vec![Cell::Water; 3];
+        cells.extend(vec![Cell::Fish { handle }; fish]);
+        Population::count_cells(tick, cells.iter(), std::iter::empty())
+    }
+
+    #[test]
+    fn history_keeps_the_latest() {
+        let mut history = PopulationHistory::new(2);
+        for tick in 0..5 {
+            history.push(census(tick, tick as usize));
+        }
+        assert_eq!(history.iter().map(|p| p.tick).collect::<Vec::<u64>>(), vec![3, 4]);
+        assert_eq!(history.latest().unwrap().count("Fish"), 4);
+
+        history.set_capacity(0);
+        history.push(census(5, 0));
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn csv_rows_match_history() {
+        let mut history = PopulationHistory::new(8);
+        let mut streamed = vec!();
+        Population::write_csv_header(&mut streamed).unwrap();
+        for tick in 0..3 {
+            let population = census(tick, 2);
+            population.write_csv_row(&mut streamed).unwrap();
+            history.push(population);
+        }
+        let mut written = vec!();
+        history.write_csv(&mut written).unwrap();
+        assert_eq!(written, streamed);
+        let text = String::from_utf8(written).unwrap();
+        assert_eq!(text.lines().count(), 4);
+        assert!(text.lines().nth(1).unwrap().starts_with("0,3,0,0,0,2,"));
+    }
+}
@@ -0,0 +1,64 @@
+use std::f32::consts::PI;
+
+use crate::world::MAX_SUN_ANGLE;
+
+/// A day and night cycle, as set with `World::set_day_cycle`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DayCycle {
+    /// Ticks from one midnight to the next.
+    pub length: u64,
+    /// Move the sun across the sky through the day, from `MAX_SUN_ANGLE` in the morning to
+    /// `-MAX_SUN_ANGLE` in the evening, instead of keeping the angle it was set to.
+    pub sweep: bool
+}
+
+// steps the daylight changes in. The lighting is worked out again whenever the sun changes, so
+// moving it in steps keeps that to a few hundred times a day however long the day is.
+const DAYLIGHT_STEPS: f32 = 60.0;
+
+/// How bright the sun is `phase` of the way through the day from midnight, from 0 to 1 in steps
+/// of a sixtieth. It is dark from 20:00 to 04:00, full from 08:00 to 16:00 and ramps up and down
+/// in between.
+pub(crate) fn daylight(phase: f32) -> f32 {
+    let elevation = -(phase * 2.0 * PI).cos();
+    ((elevation + 0.5).clamp(0.0, 1.0) * DAYLIGHT_STEPS).round() / DAYLIGHT_STEPS
+}
+
+/// Angle of a sweeping sun `phase` of the way through the day, in whole degrees. It comes up on
+/// the left at 04:00, is overhead at noon and goes down on the right at 20:00.
+pub(crate) fn sweep_angle(phase: f32) -> f32 {
+    (((0.5 - phase) * 3.0).clamp(-1.0, 1.0) * MAX_SUN_ANGLE).round()
+}
+
+/// `phase` of the way through the day as a 24 hour clock, like `06:30`.
+pub fn clock_time(phase: f32) -> String {
+    let minutes = (phase.rem_euclid(1.0) * 24.0 * 60.0) as u32 % (24 * 60);
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // how many times `f` changes over a day of `length` ticks
+    fn changes(length: u64, f: fn(f32) -> f32) -> usize {
+        (0..length)
+            .map(|t| f(t as f32 / length as f32))
+            .collect::<Vec::<_>>()
+            .windows(2)
+            .filter(|w| w[0] != w[1])
+            .count()
+    }
+
+    #[test]
+    fn sun_moves_in_steps() {
+        assert_eq!(daylight(0.0), 0.0);
+        assert_eq!(daylight(0.5), 1.0);
+        assert_eq!(sweep_angle(0.5), 0.0);
+        // a long day changes the light no more often than a short one
+        for f in [daylight as fn(f32) -> f32, sweep_angle].iter() {
+            assert_eq!(changes(100_000, *f), changes(1_000_000, *f));
+            assert!(changes(100_000, *f) <= 2 * (DAYLIGHT_STEPS as usize).max(2 * MAX_SUN_ANGLE as usize));
+        }
+    }
+}
@@ -1,9 +1,22 @@
-use crate::cell::Cell;
+use crate::cell::{Cell, Organism};
+use crate::arena::{Arena, Handle};
+use crate::registry::{Life, OrganismId, OrganismInfo};
+use crate::sun::{self, DayCycle};
+use crate::save;
+use crate::rng::WorldRng;
+use crate::stats::{Population, PopulationHistory};
 use std::cmp;
-use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::thread;
+use rand::prelude::*;
+use log::debug;
 
-pub const WORLD_WIDTH: usize = 256;
-pub const WORLD_HEIGHT: usize = 144;
+pub const DEFAULT_WORLD_WIDTH: usize = 256;
+pub const DEFAULT_WORLD_HEIGHT: usize = 144;
+/// Number of ticks of population history a new world keeps.
+pub const DEFAULT_HISTORY_LEN: usize = 4096;
 
 
 
@@ -19,51 +32,314 @@ pub const NITROGEN_COLOR : [u8; 4] = [0x33, 0x77, 0xff, 0xff];
 pub const BUBBLE_COLOR : [u8; 4] = [0x56, 0xa3, 0xfe, 0xff];
 pub const STONE_COLOR : [u8; 4] = [0x33, 0x33, 0x33, 0xff];
 pub const WORM_COLOR : [u8; 4] = [0xBB, 0x44, 0x43, 0xff];
+pub const LAMP_COLOR : [u8; 4] = [0xff, 0xf5, 0xb8, 0xff];
 
-#[inline]
-pub fn pos_to_idx(x: usize, y: usize) -> usize{
-    return (y * WORLD_WIDTH) + x;
+/// Width and height of the square chunks that sleep when nothing in them is happening.
+pub const CHUNK_SIZE: usize = 16;
+/// Furthest a resting cell looks when it updates. Sand checks the row below it and gas looks two
+/// cells up, so a change within this distance of a sleeping chunk has to wake it.
+const WAKE_DISTANCE: usize = 2;
+
+/// Light a ray loses going through a cell of clear water, out of the 15 it has at the surface at
+/// noon. Enough to leave the bottom of a default sized tank only just bright enough for plants.
+pub const WATER_ABSORPTION: f32 = 0.04;
+/// Share of the light going through a bubble that it scatters into the rays either side.
+pub const BUBBLE_SCATTER: f32 = 0.3;
+
+/// Light a lamp gives its own cell, fading to nothing `LAMP_RADIUS` cells away.
+pub const LAMP_BRIGHTNESS: f32 = 15.0;
+/// Furthest a lamp's light reaches, in cells.
+pub const LAMP_RADIUS: i32 = 12;
+
+/// Furthest the sun can be from straight overhead, in degrees either way.
+pub const MAX_SUN_ANGLE: f32 = 75.0;
+/// Cells either side that smooth lighting averages over by default.
+pub const DEFAULT_LIGHT_BLUR_RADIUS: usize = 3;
+/// Largest radius smooth lighting can average over.
+pub const MAX_LIGHT_BLUR_RADIUS: usize = 16;
+
+/// Side of the square regions the checkerboard schedule updates concurrently. Regions sharing a
+/// phase are a region apart, and each is updated with half a region of its neighbours around it,
+/// so no two threads ever touch the same cell.
+pub const REGION_SIZE: usize = 2 * CHUNK_SIZE;
+const REGION_MARGIN: usize = REGION_SIZE / 2;
+// four numbers placing a rectangle of cells, such as its left, top, right and bottom
+type Rect = (usize, usize, usize, usize);
+
+/// How `World::update` visits the cells.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// One column at a time across the whole world, on the calling thread.
+    Serial,
+    /// The world is split into regions coloured like a 2x2 checkerboard. The regions of each colour
+    /// are updated at the same time across `threads` threads, one colour after another.
+    ///
+    /// Every region draws from its own random number generator seeded from the world's, so the
+    /// result is the same for any number of threads. Organisms that reach more than
+    /// `REGION_SIZE / 2` cells from their head, such as very long worms, are clipped to their
+    /// region's surroundings. A wrapping world is only split up when its width is a multiple of
+    /// `2 * REGION_SIZE`, and is otherwise updated as `Serial`.
+    Checkerboard { threads: usize }
+}
+
+/// The order `World::update` visits the columns in. Each column is always updated from the bottom
+/// up, so falling cells move at most once a tick.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScanOrder {
+    /// Left to right every tick. Sand piles up a little more readily on the left.
+    Fixed,
+    /// Left to right on even ticks and right to left on odd ones.
+    Alternating,
+    /// A fresh random order every tick.
+    Shuffled
+}
+
+impl ScanOrder {
+    fn tag(self) -> u8 {
+        match self {
+            ScanOrder::Fixed => 0,
+            ScanOrder::Alternating => 1,
+            ScanOrder::Shuffled => 2
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(ScanOrder::Fixed),
+            1 => Some(ScanOrder::Alternating),
+            2 => Some(ScanOrder::Shuffled),
+            _ => None
+        }
+    }
 }
 
-#[inline]
-pub fn in_bounds(x: i32, y: i32) -> bool {
-    x >= 0 && x <= (WORLD_WIDTH as i32 - 1) && y >=0 && y <= (WORLD_HEIGHT as i32 - 1)
+/// What lies past the left and right edges of a world. The top and bottom are always walls.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// Nothing; the world is a glass box and fish turn round at the sides.
+    Walls,
+    /// The other side. A fish swimming off the right edge comes back on the left, and bodies can
+    /// straddle the seam.
+    WrapX
 }
 
-#[inline]
-fn clamp_to_bounds(xy: (i32, i32)) -> (usize, usize) {
-    (cmp::min(cmp::max(xy.0, 0), WORLD_WIDTH as i32 -1) as usize, cmp::min(cmp::max(xy.1, 0), WORLD_HEIGHT as i32 - 1) as usize)
+/// What happens to a cell written outside the world, like the body of a fish swimming along the
+/// wall or a kelp leaf growing past it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutOfBounds {
+    /// Move the write onto the nearest edge cell, overwriting whatever is there. How worlds always
+    /// used to behave.
+    Clamp,
+    /// Throw the write away.
+    Drop,
+    /// Throw the write away and log the cell and where it was going at debug level.
+    Log
 }
 
+/// The grid a `World` simulates. Cell updates address it relative to the cell being updated.
 pub struct Cells {
+    width: usize,
+    height: usize,
     inner: Vec::<Cell>,
+    // state of the organisms whose head cells are in `inner`
+    organisms: Arena::<Organism>,
+    // who each organism in `organisms` is, indexed by handle
+    lives: Vec::<Life>,
+    // the ID the next organism born will get
+    next_id: u64,
+    // light reaching each cell, as the cells see it
     lighting: Vec::<u8>,
+    // scratch for working out `lighting`, and then the smoothed lighting `draw` shows
     lighting_tmp: Vec::<u8>,
+    // set when something that affects the lighting has changed since it was last worked out
+    lighting_stale: bool,
+    // blur radius `lighting_tmp` was smoothed with, if it is still up to date
+    smoothed: Option::<usize>,
+    // degrees from straight down the sunlight comes in at, positive when it slants to the right
+    sun_angle: f32,
+    // how bright the sun is, from 0 at night to 1 at noon
+    daylight: f32,
+    // cells either side of each cell that smooth lighting averages over
+    light_blur_radius: usize,
+    // sums of each row of the blur window, kept to save allocating every frame
+    light_sums: Vec::<u32>,
     current_offset: (i32, i32),
-    ignored: HashSet<(i32, i32)>
+    // a cell has been updated this tick when its stamp equals the current generation
+    stamps: Vec::<u32>,
+    generation: u32,
+    rng: WorldRng,
+    chunks_x: usize,
+    chunks_y: usize,
+    // chunks updated this tick, and chunks that must be updated next tick
+    awake: Vec::<bool>,
+    wake_next: Vec::<bool>,
+    sleeping: bool,
+    wrap_x: bool,
+    out_of_bounds: OutOfBounds,
+    // writes that went outside the world and weren't clamped
+    stray_writes: u64,
+    // which sides are the edge of the world, as left, top, right and bottom. On a window copied
+    // out for the checkerboard schedule the others border the rest of the world
+    edges: [bool; 4],
+    // on a window, the strays to report when it's merged back
+    strays: Vec::<(&'static str, (i32, i32))>,
+    // set on a window copied out for the checkerboard schedule: positions whose changes reach past
+    // the window's edge, so the chunks beyond can be woken when it's merged back
+    spill: Option::<Vec::<(usize, usize)>>,
+    // on a window, the smallest rectangle holding every cell written or ignored
+    dirty: Option::<(usize, usize, usize, usize)>,
+    // on a window, the world's handle for each organism copied into it, indexed by the window's handle
+    origins: Vec::<Handle>
 }
 
 impl Cells {
-    fn new() -> Self {
+    fn new(width: usize, height: usize, rng: WorldRng) -> Self {
+        let chunks_x = width.div_ceil(CHUNK_SIZE);
+        let chunks_y = height.div_ceil(CHUNK_SIZE);
         Self {
-            inner: vec![Cell::Water; WORLD_WIDTH * WORLD_HEIGHT],
-            lighting: vec![15u8; WORLD_WIDTH * WORLD_HEIGHT],
-            lighting_tmp: vec![15u8; WORLD_WIDTH * WORLD_HEIGHT],
+            width,
+            height,
+            inner: vec![Cell::Water; width * height],
+            organisms: Arena::new(),
+            lives: vec!(),
+            next_id: 0,
+            lighting: vec![15u8; width * height],
+            lighting_tmp: vec![15u8; width * height],
+            lighting_stale: true,
+            smoothed: None,
+            sun_angle: 0.0,
+            daylight: 1.0,
+            light_blur_radius: DEFAULT_LIGHT_BLUR_RADIUS,
+            light_sums: vec!(),
             current_offset: (0, 0),
-            ignored: HashSet::new(),
+            stamps: vec![0; width * height],
+            generation: 1,
+            rng,
+            chunks_x,
+            chunks_y,
+            awake: vec![true; chunks_x * chunks_y],
+            wake_next: vec![true; chunks_x * chunks_y],
+            sleeping: true,
+            wrap_x: false,
+            out_of_bounds: OutOfBounds::Drop,
+            stray_writes: 0,
+            edges: [true; 4],
+            strays: vec!(),
+            spill: None,
+            dirty: None,
+            origins: vec!()
+        }
+    }
+
+    #[inline]
+    fn pos_to_idx(&self, x: usize, y: usize) -> usize{
+        (y * self.width) + x
+    }
+
+    #[inline]
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && x <= (self.width as i32 - 1) && y >=0 && y <= (self.height as i32 - 1)
+    }
+
+    #[inline]
+    fn clamp_to_bounds(&self, xy: (i32, i32)) -> (usize, usize) {
+        (cmp::min(cmp::max(xy.0, 0), self.width as i32 -1) as usize, cmp::min(cmp::max(xy.1, 0), self.height as i32 - 1) as usize)
+    }
+
+    /// World position `(dx, dy)` from the cell being updated, with x wrapped round if the world wraps.
+    #[inline]
+    fn offset_pos(&self, dx: i32, dy: i32) -> (i32, i32) {
+        let x = self.current_offset.0 + dx;
+        let x = if self.wrap_x { x.rem_euclid(self.width as i32) } else { x };
+        (x, self.current_offset.1 + dy)
+    }
+
+    /// The world's random number generator. All cell behaviour must draw from this
+    /// so that a seeded world replays identically.
+    pub fn rng(&mut self) -> &mut WorldRng {
+        // a cell that rolls the dice might do something next tick, even if it did nothing now
+        let (x, y) = self.current_offset;
+        if self.in_bounds(x, y) {
+            let chunk = self.chunk_of(x as usize, y as usize);
+            self.wake_next[chunk] = true;
+        }
+        &mut self.rng
+    }
+
+    #[inline]
+    fn chunk_of(&self, x: usize, y: usize) -> usize {
+        (y / CHUNK_SIZE) * self.chunks_x + x / CHUNK_SIZE
+    }
+
+    /// Wake every chunk a cell at `(x, y)` can affect, for the rest of this tick and the next.
+    fn touch(&mut self, x: usize, y: usize) {
+        self.mark_dirty(x, y);
+        if let Some(spill) = &mut self.spill {
+            if x < WAKE_DISTANCE || y < WAKE_DISTANCE || x + WAKE_DISTANCE >= self.width || y + WAKE_DISTANCE >= self.height {
+                spill.push((x, y));
+            }
+        }
+        // the wake distance is less than a chunk, so these cover every chunk in reach
+        let columns = if self.wrap_x {
+            let x = x as i32;
+            let width = self.width as i32;
+            [(x - WAKE_DISTANCE as i32).rem_euclid(width) as usize, x as usize, (x + WAKE_DISTANCE as i32).rem_euclid(width) as usize]
+        }
+        else {
+            [x.saturating_sub(WAKE_DISTANCE), x, cmp::min(x + WAKE_DISTANCE, self.width - 1)]
+        };
+        let cy0 = y.saturating_sub(WAKE_DISTANCE) / CHUNK_SIZE;
+        let cy1 = cmp::min(y + WAKE_DISTANCE, self.height - 1) / CHUNK_SIZE;
+        for cy in cy0..=cy1 {
+            for column in columns.iter() {
+                let chunk = cy * self.chunks_x + column / CHUNK_SIZE;
+                self.awake[chunk] = true;
+                self.wake_next[chunk] = true;
+            }
+        }
+    }
+
+    #[inline]
+    fn mark_dirty(&mut self, x: usize, y: usize) {
+        if self.spill.is_some() {
+            self.dirty = Some(match self.dirty {
+                Some((x0, y0, x1, y1)) => (cmp::min(x0, x), cmp::min(y0, y), cmp::max(x1, x), cmp::max(y1, y)),
+                None => (x, y, x, y)
+            });
+        }
+    }
+
+    /// Start a tick: wake the chunks that asked to be, and let the rest sleep.
+    fn swap_awake(&mut self) {
+        std::mem::swap(&mut self.awake, &mut self.wake_next);
+        let sleeping = self.sleeping;
+        self.wake_next.iter_mut().for_each(|w| *w = !sleeping);
+        if !sleeping {
+            self.awake.iter_mut().for_each(|w| *w = true);
         }
     }
 
     fn reset_ignored(&mut self) {
-        self.ignored.clear();
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            // stamps from 2^32 ticks ago would look current, so clear them
+            self.stamps.iter_mut().for_each(|s| *s = 0);
+            self.generation = 1;
+        }
     }
 
     pub fn ignore(&mut self, x: i32, y: i32) {
-        self.ignored.insert((self.current_offset.0 + x, self.current_offset.1 + y));
+        let (x, y) = self.offset_pos(x, y);
+        if self.in_bounds(x, y) {
+            let idx = self.pos_to_idx(x as usize, y as usize);
+            self.stamps[idx] = self.generation;
+            self.mark_dirty(x as usize, y as usize);
+        }
     }
 
     fn ignored(&self, x: i32, y:i32) -> bool {
-        self.ignored.get(&(self.current_offset.0 + x, self.current_offset.1 + y)).is_some()
+        let (x, y) = self.offset_pos(x, y);
+        self.in_bounds(x, y) && self.stamps[self.pos_to_idx(x as usize, y as usize)] == self.generation
     }
 
     fn set_offset(&mut self, x: i32, y: i32) {
@@ -75,76 +351,521 @@ impl Cells {
     }
 
     pub fn get(&self, dx: i32, dy: i32) -> Option<&Cell> {
-        let (mut x, mut y) = (self.current_offset.0 + dx, self.current_offset.1 + dy);
-        if !in_bounds(x, y) {
+        let (x, y) = self.offset_pos(dx, dy);
+        if !self.in_bounds(x, y) {
             None
         }
         else {
-            let (nx, ny) = clamp_to_bounds((x, y));
-            let idx = pos_to_idx(nx, ny);
+            let (nx, ny) = self.clamp_to_bounds((x, y));
+            let idx = self.pos_to_idx(nx, ny);
             Some(&self.inner[idx])
         }        
     }
 
+    /// Decide what to do with a write of `name` to `pos`, which is outside the grid. Returns true
+    /// if it should be clamped onto the edge, and reports it if it is dropped.
+    fn clamp_stray(&mut self, name: &'static str, pos: (i32, i32)) -> bool {
+        let [left, top, right, bottom] = self.edges;
+        let (x, y) = pos;
+        let past_world = (left && x < 0) || (top && y < 0) || (right && x >= self.width as i32) || (bottom && y >= self.height as i32);
+        if !past_world {
+            // past the edge of a window, another region's cells
+            return false;
+        }
+        if self.out_of_bounds == OutOfBounds::Clamp {
+            return true;
+        }
+        if self.spill.is_some() {
+            self.strays.push((name, pos));
+        }
+        else {
+            self.report_stray(name, pos);
+        }
+        false
+    }
+
+    fn report_stray(&mut self, name: &'static str, pos: (i32, i32)) {
+        self.stray_writes += 1;
+        if self.out_of_bounds == OutOfBounds::Log {
+            debug!("dropped {} written outside the world at ({}, {})", name, pos.0, pos.1);
+        }
+    }
+
     pub fn set(&mut self, dx: i32, dy: i32, cell: Cell) {
-        let (x, y) = clamp_to_bounds((self.current_offset.0 + dx, self.current_offset.1 + dy));
-        let idx = pos_to_idx(x, y);
+        self.place(dx, dy, cell);
+    }
+
+    /// Write `cell` as `set` does, and return whether it was written rather than dropped.
+    fn place(&mut self, dx: i32, dy: i32, cell: Cell) -> bool {
+        let pos = self.offset_pos(dx, dy);
+        if !self.in_bounds(pos.0, pos.1) && !self.clamp_stray(cell.name(), pos) {
+            return false;
+        }
+        let (x, y) = self.clamp_to_bounds(pos);
+        let idx = self.pos_to_idx(x, y);
+        self.lighting_stale |= !same_light(&self.inner[idx], &cell);
         self.inner[idx] = cell;
+        self.touch(x, y);
+        true
+    }
+
+    /// State of the organism `handle` points to. An organism that is updating can't see itself.
+    pub fn organism(&self, handle: Handle) -> Option<&Organism> {
+        self.organisms.get(handle)
+    }
+
+    /// Move an organism's state out for it to update. It is gone unless `set_organism` or
+    /// `put_organism` puts it back.
+    pub(crate) fn take_organism(&mut self, handle: Handle) -> Option<Organism> {
+        self.organisms.take(handle)
+    }
+
+    /// Put an organism's state back and place its head cell at `(dx, dy)`. If that is outside the
+    /// world and the write is dropped, nothing is placed and the organism is handed back.
+    pub(crate) fn set_organism(&mut self, dx: i32, dy: i32, handle: Handle, organism: Organism) -> Result<(), Organism> {
+        if !self.place(dx, dy, organism.cell(handle)) {
+            return Err(organism);
+        }
+        self.organisms.put(handle, organism);
+        Ok(())
+    }
+
+    /// Put an organism's state back with its head cell where it was.
+    pub(crate) fn put_organism(&mut self, handle: Handle, organism: Organism) {
+        let cell = organism.cell(handle);
+        self.organisms.put(handle, organism);
+        self.set(0, 0, cell);
+    }
+
+    /// Move an organism's head cell to `(dx, dy)`, or leave it where it was if that is outside the
+    /// world and the write is dropped.
+    pub(crate) fn move_organism(&mut self, dx: i32, dy: i32, handle: Handle, organism: Organism) {
+        if let Err(organism) = self.set_organism(dx, dy, handle, organism) {
+            self.put_organism(handle, organism);
+        }
+    }
+
+    /// Place a newborn organism with its head cell at `(dx, dy)`.
+    pub(crate) fn new_organism(&mut self, dx: i32, dy: i32, organism: Organism) {
+        let id = self.new_id();
+        self.place_organism(dx, dy, organism, id);
+    }
+
+    /// Place a newborn organism called `id` with its head cell at `(dx, dy)`.
+    fn place_organism(&mut self, dx: i32, dy: i32, organism: Organism, id: OrganismId) {
+        let handle = self.organisms.insert(organism);
+        self.set_life(handle, Life { id, age: 0 });
+        let cell = self.organisms.get(handle).unwrap().cell(handle);
+        self.set(dx, dy, cell);
+    }
+
+    fn new_id(&mut self) -> OrganismId {
+        self.next_id += 1;
+        OrganismId(self.next_id - 1)
+    }
+
+    fn set_life(&mut self, handle: Handle, life: Life) {
+        if handle.index() >= self.lives.len() {
+            self.lives.resize(handle.index() + 1, Life::default());
+        }
+        self.lives[handle.index()] = life;
+    }
+
+    /// Free the state of organisms whose head cell has been overwritten, and age the rest a tick.
+    fn collect_organisms(&mut self) {
+        self.organisms.retain(self.inner.iter().filter_map(Cell::handle));
+        for (handle, _) in self.organisms.iter() {
+            self.lives[handle.index()].age += 1;
+        }
     }
 
     pub fn get_light(&mut self, dx: i32, dy: i32) -> u8 {
-        let (x, y) = clamp_to_bounds((self.current_offset.0 + dx, self.current_offset.1 + dy));
-        let idx = pos_to_idx(x, y);
+        let (x, y) = self.clamp_to_bounds(self.offset_pos(dx, dy));
+        let idx = self.pos_to_idx(x, y);
         self.lighting[idx]
     }
 
     pub fn swap(&mut self, dx1: i32, dy1: i32, dx2: i32, dy2: i32) {
-        let (x1, y1) = clamp_to_bounds((self.current_offset.0 + dx1, self.current_offset.1 + dy1));
-        let (x2, y2) = clamp_to_bounds((self.current_offset.0 + dx2, self.current_offset.1 + dy2));
-        self.inner.swap(
-            pos_to_idx(x1, y1),
-            pos_to_idx(x2, y2)
-        );
-    }
-
-    pub fn calc_shadow(&mut self, smooth_lighting: bool) {
-        // TODO use cos/sin for angled lighting
-
-        for x in 0..WORLD_WIDTH {
-            let mut s = 15u8;
-            for y in 0..WORLD_HEIGHT {
-                let i = pos_to_idx(x, y);
-                self.lighting_tmp[i] = s;
-                match self.inner[i] {
-                    Cell::Stone => { s = s.saturating_sub(8) },
-                    _ => {}
-                }
-            }
-        }
-
-        if smooth_lighting {
-            for x in 0..WORLD_WIDTH {
-                for y in 0..WORLD_HEIGHT {
-                    let i = pos_to_idx(x, y);                
-                    let mut v = vec!();
-                    for dx in -3..3 {
-                        for dy in -3..3 {
-                            let (nx, ny) = clamp_to_bounds(((x as i32) + dx, (y as i32) + dy));
-                            let i = pos_to_idx(nx, ny);
-                            v.push(self.lighting_tmp[i] as u16)
+        let (pos1, pos2) = (self.offset_pos(dx1, dy1), self.offset_pos(dx2, dy2));
+        // whichever end is outside, the cell at the other end is what would be moved there
+        for (from, to) in [((dx1, dy1), pos2), ((dx2, dy2), pos1)].iter().copied() {
+            if !self.in_bounds(to.0, to.1) {
+                let name = self.get(from.0, from.1).map_or("Water", Cell::name);
+                if !self.clamp_stray(name, to) {
+                    return;
+                }
+            }
+        }
+        let (x1, y1) = self.clamp_to_bounds(pos1);
+        let (x2, y2) = self.clamp_to_bounds(pos2);
+        let (i1, i2) = (self.pos_to_idx(x1, y1), self.pos_to_idx(x2, y2));
+        self.lighting_stale |= !same_light(&self.inner[i1], &self.inner[i2]);
+        self.inner.swap(i1, i2);
+        self.touch(x1, y1);
+        self.touch(x2, y2);
+    }
+
+    /// Columns `x0` up to `x1` in the order they should be updated on `tick`.
+    fn scan_columns(&mut self, x0: usize, x1: usize, order: ScanOrder, tick: u64) -> Vec::<usize> {
+        let mut columns: Vec::<usize> = (x0..x1).collect();
+        match order {
+            ScanOrder::Fixed => {},
+            ScanOrder::Alternating => {
+                if tick % 2 == 1 {
+                    columns.reverse();
+                }
+            },
+            // straight from the generator so no chunk is woken by it
+            ScanOrder::Shuffled => columns.shuffle(&mut self.rng)
+        }
+        columns
+    }
+
+    /// Update every awake cell of `columns` between rows `y0` and `y1`, a column at a time from
+    /// the bottom up. `y0` must be on a chunk boundary.
+    fn update_area(&mut self, columns: &[usize], y0: usize, y1: usize) {
+        for &x in columns {
+            let cx = x / CHUNK_SIZE;
+            for cy in ((y0 / CHUNK_SIZE)..y1.div_ceil(CHUNK_SIZE)).rev() {
+                // a sleeping chunk's cells would all do nothing, skip them
+                if !self.awake[cy * self.chunks_x + cx] {
+                    continue;
+                }
+                let rows = (cy * CHUNK_SIZE)..cmp::min((cy + 1) * CHUNK_SIZE, y1);
+                for y in rows.rev() {
+                    self.set_offset(0, 0);
+                    if !self.ignored(x as i32, y as i32) {
+                        let cell = *self.get(x as i32, y as i32).unwrap();
+                        self.set_offset(x as i32, y as i32);
+                        cell.update(self);
+                    }                
+                }
+            }
+        }
+    }
+
+    /// Whether the checkerboard schedule can split this world into regions. Across the seam of a
+    /// wrapping world, regions only alternate and chunks only line up when the width is a whole
+    /// number of region pairs.
+    fn checkerboard_fits(&self) -> bool {
+        !self.wrap_x || self.width.is_multiple_of(2 * REGION_SIZE)
+    }
+
+    /// Bounds of region `(rx, ry)`, and the left, top, width and height of the window around it
+    /// that its update may touch. In a wrapping world the window can run over the seam.
+    fn region_bounds(&self, rx: usize, ry: usize) -> (Rect, Rect) {
+        let (x0, y0) = (rx * REGION_SIZE, ry * REGION_SIZE);
+        let region = (x0, y0, cmp::min(x0 + REGION_SIZE, self.width), cmp::min(y0 + REGION_SIZE, self.height));
+        let (wx0, width) = if self.wrap_x {
+            ((x0 + self.width - REGION_MARGIN) % self.width, REGION_SIZE + 2 * REGION_MARGIN)
+        }
+        else {
+            let wx0 = x0.saturating_sub(REGION_MARGIN);
+            (wx0, cmp::min(x0 + REGION_SIZE + REGION_MARGIN, self.width) - wx0)
+        };
+        let wy0 = y0.saturating_sub(REGION_MARGIN);
+        let height = cmp::min(y0 + REGION_SIZE + REGION_MARGIN, self.height) - wy0;
+        (region, (wx0, wy0, width, height))
+    }
+
+    fn region_awake(&self, rx: usize, ry: usize) -> bool {
+        let ((x0, y0, x1, y1), _) = self.region_bounds(rx, ry);
+        ((y0 / CHUNK_SIZE)..y1.div_ceil(CHUNK_SIZE)).any(|cy| {
+            ((x0 / CHUNK_SIZE)..x1.div_ceil(CHUNK_SIZE)).any(|cx| self.awake[cy * self.chunks_x + cx])
+        })
+    }
+
+    /// Copy out the window around region `(rx, ry)` and update the region inside it.
+    fn update_region(&self, rx: usize, ry: usize, seed: u64, order: ScanOrder, tick: u64) -> Cells {
+        let ((x0, y0, x1, y1), (wx0, wy0, width, height)) = self.region_bounds(rx, ry);
+        let chunks_x = width.div_ceil(CHUNK_SIZE);
+        let chunks_y = height.div_ceil(CHUNK_SIZE);
+        let mut window = Cells {
+            width,
+            height,
+            inner: Vec::with_capacity(width * height),
+            organisms: Arena::new(),
+            lives: vec!(),
+            // newborns in a window are given their IDs when it's merged back
+            next_id: 0,
+            lighting: Vec::with_capacity(width * height),
+            lighting_tmp: vec!(),
+            lighting_stale: false,
+            smoothed: None,
+            sun_angle: self.sun_angle,
+            daylight: self.daylight,
+            light_blur_radius: self.light_blur_radius,
+            light_sums: vec!(),
+            current_offset: (0, 0),
+            stamps: Vec::with_capacity(width * height),
+            generation: self.generation,
+            rng: WorldRng::seed_from_u64(seed),
+            chunks_x,
+            chunks_y,
+            awake: Vec::with_capacity(chunks_x * chunks_y),
+            wake_next: Vec::with_capacity(chunks_x * chunks_y),
+            sleeping: self.sleeping,
+            wrap_x: false,
+            out_of_bounds: self.out_of_bounds,
+            stray_writes: 0,
+            edges: [
+                !self.wrap_x && wx0 == 0,
+                wy0 == 0,
+                !self.wrap_x && wx0 + width == self.width,
+                wy0 + height == self.height
+            ],
+            strays: vec!(),
+            spill: Some(vec!()),
+            dirty: None,
+            origins: vec!()
+        };
+        for y in wy0..(wy0 + height) {
+            for (start, len) in runs(wx0, width, self.width).iter() {
+                let row = self.pos_to_idx(*start, y)..self.pos_to_idx(start + len, y);
+                window.inner.extend_from_slice(&self.inner[row.clone()]);
+                window.lighting.extend_from_slice(&self.lighting[row.clone()]);
+                window.stamps.extend_from_slice(&self.stamps[row]);
+            }
+        }
+        // the window gets its own copy of every organism in it, with handles of its own
+        for cell in window.inner.iter_mut() {
+            if let Some(organism) = cell.handle().and_then(|h| self.organisms.get(h)) {
+                let handle = cell.handle().unwrap();
+                window.origins.push(handle);
+                window.lives.push(self.lives[handle.index()]);
+                *cell = cell.with_handle(window.organisms.insert(organism.clone()));
+            }
+        }
+        for cy in (wy0 / CHUNK_SIZE)..(wy0 / CHUNK_SIZE + chunks_y) {
+            for (start, len) in runs(wx0 / CHUNK_SIZE, chunks_x, self.chunks_x).iter() {
+                let row = (cy * self.chunks_x + start)..(cy * self.chunks_x + start + len);
+                window.awake.extend_from_slice(&self.awake[row.clone()]);
+                window.wake_next.extend_from_slice(&self.wake_next[row]);
+            }
+        }
+
+        let left = (x0 + self.width - wx0) % self.width;
+        let columns = window.scan_columns(left, left + x1 - x0, order, tick);
+        window.update_area(&columns, y0 - wy0, y1 - wy0);
+        window
+    }
+
+    /// Write back what changed in a window updated by `update_region`.
+    fn merge_region(&mut self, rx: usize, ry: usize, mut window: Cells) {
+        let (_, (wx0, wy0, _, _)) = self.region_bounds(rx, ry);
+        // organisms copied in go back to their old handles and newborns get new handles and IDs,
+        // in the order they were born. Those that died in the window are freed at the end of the
+        // tick, like any other
+        let mut handles = vec![None; window.organisms.slot_count()];
+        for (local, organism) in window.organisms.drain() {
+            let handle = match window.origins.get(local.index()) {
+                Some(&handle) => {
+                    self.organisms.put(handle, organism);
+                    handle
+                },
+                None => {
+                    let handle = self.organisms.insert(organism);
+                    let id = self.new_id();
+                    self.set_life(handle, Life { id, age: window.lives[local.index()].age });
+                    handle
+                }
+            };
+            handles[local.index()] = Some(handle);
+        }
+        if let Some((x0, y0, x1, y1)) = window.dirty {
+            for wy in y0..=y1 {
+                for wx in x0..=x1 {
+                    let (i, j) = (self.pos_to_idx((wx0 + wx) % self.width, wy0 + wy), window.pos_to_idx(wx, wy));
+                    let cell = window.inner[j];
+                    let cell = match cell.handle() {
+                        Some(local) => handles.get(local.index()).copied().flatten().map_or(Cell::Water, |h| cell.with_handle(h)),
+                        None => cell
+                    };
+                    self.lighting_stale |= !same_light(&self.inner[i], &cell);
+                    self.inner[i] = cell;
+                    self.stamps[i] = window.stamps[j];
+                }
+            }
+        }
+        // chunks are only ever woken during a tick, and a neighbour merged earlier may have woken
+        // some of these through its spill
+        for cy in 0..window.chunks_y {
+            for cx in 0..window.chunks_x {
+                let chunk = (wy0 / CHUNK_SIZE + cy) * self.chunks_x + (wx0 / CHUNK_SIZE + cx) % self.chunks_x;
+                self.awake[chunk] |= window.awake[cy * window.chunks_x + cx];
+                self.wake_next[chunk] |= window.wake_next[cy * window.chunks_x + cx];
+            }
+        }
+        for (x, y) in window.spill.take().unwrap_or_default() {
+            self.touch((wx0 + x) % self.width, wy0 + y);
+        }
+        for (name, (x, y)) in window.strays.drain(..) {
+            self.report_stray(name, (wx0 as i32 + x, wy0 as i32 + y));
+        }
+    }
+
+    /// Work out how much light reaches each cell, as read by `get_light`: sunlight, and the light
+    /// of lamps as worked out by `add_point_lights`.
+    ///
+    /// Rays come down at the sun angle a row at a time, and each cell takes the light of the ray
+    /// through it from where that ray crossed the row above, blending the two cells it fell
+    /// between. Every cell a ray goes through takes some of its light, as given by `absorption`,
+    /// and bubbles scatter some into the rays either side. Rays that leave the side of the world
+    /// are gone. Those coming in from the side arrive as if through clear water, or from the other
+    /// side if the world wraps.
+    ///
+    /// `World::update` does this whenever something that affects the lighting has changed, so a
+    /// world lights up the same whether or not it is drawn.
+    pub fn calc_shadow(&mut self) {
+        let (width, wrap_x) = (self.width as i32, self.wrap_x);
+        let shift = self.sun_angle.to_radians().tan();
+        let surface = 15.0 * self.daylight;
+        // light left in each ray below the row above, and below this row
+        let mut above = vec![surface; self.width];
+        let mut below = vec![0f32; self.width];
+        for y in 0..self.height {
+            let outside = (surface - WATER_ABSORPTION * y as f32).max(0.0);
+            let ray = |row: &[f32], x: i32| -> f32 {
+                if wrap_x {
+                    row[x.rem_euclid(width) as usize]
+                }
+                else if x < 0 || x >= width {
+                    outside
+                }
+                else {
+                    row[x as usize]
+                }
+            };
+            for (x, b) in below.iter_mut().enumerate() {
+                let from = x as f32 - shift;
+                let (left, t) = (from.floor(), from - from.floor());
+                let s = ray(&above, left as i32) * (1.0 - t) + ray(&above, left as i32 + 1) * t;
+                let i = self.pos_to_idx(x, y);
+                // scattered light can add up to more than the surface has
+                self.lighting_tmp[i] = s.round().min(15.0) as u8;
+                *b = (s - absorption(&self.inner[i])).max(0.0);
+            }
+            for x in 0..self.width {
+                if self.inner[self.pos_to_idx(x, y)] != Cell::Bubble {
+                    continue;
+                }
+                let share = below[x] * BUBBLE_SCATTER / 2.0;
+                below[x] -= 2.0 * share;
+                for side in [x as i32 - 1, x as i32 + 1].iter() {
+                    // scattered out through the glass, it's lost
+                    if wrap_x || (*side >= 0 && *side < width) {
+                        below[side.rem_euclid(width) as usize] += share;
+                    }
+                }
+            }
+            std::mem::swap(&mut above, &mut below);
+        }
+        self.add_point_lights();
+        std::mem::swap(&mut self.lighting, &mut self.lighting_tmp);
+        self.lighting_stale = false;
+        self.smoothed = None;
+    }
+
+    /// Add the light of every cell that glows into `lighting_tmp`. It fades in a straight line from
+    /// the source to nothing at its radius, and on the way each cell it goes through takes the
+    /// light it would take from the sun, so stone keeps it out of what is behind.
+    fn add_point_lights(&mut self) {
+        let (width, height, wrap_x) = (self.width as i32, self.height as i32, self.wrap_x);
+        // column a world x is at, if it is in the world
+        let col = |x: i32| -> Option<usize> {
+            if wrap_x { Some(x.rem_euclid(width) as usize) }
+            else if x >= 0 && x < width { Some(x as usize) }
+            else { None }
+        };
+        let sources: Vec::<_> = (0..self.inner.len())
+            .filter_map(|i| glow(&self.inner[i]).map(|g| ((i % self.width) as i32, (i / self.width) as i32, g)))
+            .collect();
+        for (sx, sy, (brightness, radius)) in sources {
+            // in a wrapping world narrower than the light, each column is lit from the nearer side
+            // only, rather than from both
+            let (left, right) = if wrap_x && 2 * radius + 1 > width { (-(width - 1) / 2, width / 2) } else { (-radius, radius) };
+            for dy in -radius..=radius {
+                let y = sy + dy;
+                if y < 0 || y >= height {
+                    continue;
+                }
+                for dx in left..=right {
+                    let x = match col(sx + dx) {
+                        Some(x) => x,
+                        None => continue
+                    };
+                    let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                    let mut light = brightness * (1.0 - distance / radius as f32);
+                    // march along the line from the source, stopping short of this cell
+                    let steps = cmp::max(dx.abs(), dy.abs());
+                    for step in 1..steps {
+                        if light <= 0.0 {
+                            break;
+                        }
+                        let t = step as f32 / steps as f32;
+                        let px = sx + (dx as f32 * t).round() as i32;
+                        let py = sy + (dy as f32 * t).round() as i32;
+                        if let Some(px) = col(px) {
+                            light -= absorption(&self.inner[self.pos_to_idx(px, py as usize)]);
                         }
                     }
-                    self.lighting[i] = (v.iter().sum::<u16>() / v.len() as u16) as u8;
+                    if light > 0.0 {
+                        let i = self.pos_to_idx(x, y as usize);
+                        self.lighting_tmp[i] = (self.lighting_tmp[i] as f32 + light).round().min(15.0) as u8;
+                    }
                 }
             }
-        }    
-        else {
-            std::mem::swap(&mut self.lighting, &mut self.lighting_tmp);
-        }    
+        }
+    }
+
+    /// Set `lighting_tmp` to the average of `lighting` over a square `light_blur_radius` cells
+    /// either side of each cell. The edges of the world are repeated outwards, except across the
+    /// seam of a wrapping world. Done as a box blur along rows and then columns, keeping a running
+    /// sum so it costs the same whatever the radius.
+    fn blur_lighting(&mut self) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let r = self.light_blur_radius as i32;
+        let wrap_x = self.wrap_x;
+        let col = |x: i32| -> usize {
+            if wrap_x { x.rem_euclid(width) as usize } else { x.clamp(0, width - 1) as usize }
+        };
+        let row = |y: i32| -> usize { y.clamp(0, height - 1) as usize };
+        self.light_sums.resize(self.inner.len(), 0);
+
+        for y in 0..self.height {
+            let line = &self.lighting[y * self.width..(y + 1) * self.width];
+            let mut sum: u32 = (-r..=r).map(|dx| line[col(dx)] as u32).sum();
+            for x in 0..width {
+                self.light_sums[y * self.width + x as usize] = sum;
+                sum += line[col(x + r + 1)] as u32;
+                sum -= line[col(x - r)] as u32;
+            }
+        }
+
+        let area = ((2 * r + 1) * (2 * r + 1)) as u32;
+        for x in 0..self.width {
+            let mut sum: u32 = (-r..=r).map(|dy| self.light_sums[row(dy) * self.width + x]).sum();
+            for y in 0..height {
+                // rounded to nearest
+                self.lighting_tmp[y as usize * self.width + x] = ((sum + area / 2) / area) as u8;
+                sum += self.light_sums[row(y + r + 1) * self.width + x];
+                sum -= self.light_sums[row(y - r) * self.width + x];
+            }
+        }
     }
 
+    /// Render the cells into `fb`, shaded by their lighting. Smoothing the lighting only changes
+    /// how it looks, the cells still see it unsmoothed.
     pub fn draw(&mut self, fb : &mut [u8], smooth_lighting: bool) {
-        self.calc_shadow(smooth_lighting);
+        if self.lighting_stale {
+            self.calc_shadow();
+        }
+        let smooth_lighting = smooth_lighting && self.light_blur_radius > 0;
+        if smooth_lighting && self.smoothed != Some(self.light_blur_radius) {
+            self.blur_lighting();
+            self.smoothed = Some(self.light_blur_radius);
+        }
+        let lighting = if smooth_lighting { &self.lighting_tmp } else { &self.lighting };
         for i in 0..self.inner.len() {
             let pixel = &mut fb[i * 4..(i * 4) + 4];
             let mut color = match self.inner[i] {
@@ -172,7 +893,7 @@ impl Cells {
                 Cell::Algae {..} => {
                     ALGAE_COLOR
                 },
-                Cell::Nitrogen {..} => {
+                Cell::Nitrogen => {
                     NITROGEN_COLOR
                 },
                 Cell::Bubble | Cell::Fizzer => {
@@ -183,9 +904,12 @@ impl Cells {
                 },
                 Cell::Worm {..} | Cell::WormBody => {
                     WORM_COLOR
+                },
+                Cell::Lamp => {
+                    LAMP_COLOR
                 }
             };
-            let l = self.lighting[i];
+            let l = lighting[i];
             let s = (15 - l) * 8;
             color[0] = color[0].saturating_sub(s);
             color[1] = color[1].saturating_sub(s);
@@ -195,48 +919,1032 @@ impl Cells {
     }
 }
 
+/// Light a ray loses going through `cell`, out of the 15 it has at the surface at noon. Stone
+/// blocks most of it, and living things and decay shade what is below them.
+fn absorption(cell: &Cell) -> f32 {
+    match cell {
+        Cell::Stone | Cell::Lamp => 8.0,
+        Cell::KelpLeaf => 2.0,
+        Cell::Algae {..} | Cell::Plant {..} => 1.5,
+        Cell::Kelp {..} | Cell::Decay => 1.0,
+        _ => WATER_ABSORPTION
+    }
+}
+
+/// Whether putting `b` where `a` was leaves the lighting as it was.
+fn same_light(a: &Cell, b: &Cell) -> bool {
+    absorption(a) == absorption(b) && (*a == Cell::Bubble) == (*b == Cell::Bubble) && glow(a) == glow(b)
+}
+
+/// Brightness and radius of the light `cell` gives off, if it glows.
+fn glow(cell: &Cell) -> Option::<(f32, i32)> {
+    match cell {
+        Cell::Lamp => Some((LAMP_BRIGHTNESS, LAMP_RADIUS)),
+        _ => None
+    }
+}
+
+/// Split `count` items from `start` onwards, wrapping round after `total`, into at most two runs
+/// of `(start, length)`.
+fn runs(start: usize, count: usize, total: usize) -> [(usize, usize); 2] {
+    let first = cmp::min(count, total - start);
+    [(start, first), (0, count - first)]
+}
+
+/// Why an ASCII scene couldn't be read. Rows and columns count from zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsciiError {
+    Empty,
+    RaggedRow { row: usize, width: usize, expected: usize },
+    UnknownChar { row: usize, column: usize, c: char }
+}
+
+impl fmt::Display for AsciiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsciiError::Empty => write!(f, "scene has no rows"),
+            AsciiError::RaggedRow { row, width, expected } => {
+                write!(f, "row {} is {} cells wide, expected {}", row, width, expected)
+            },
+            AsciiError::UnknownChar { row, column, c } => {
+                write!(f, "unknown cell '{}' at row {}, column {}", c, row, column)
+            }
+        }
+    }
+}
+
+impl Error for AsciiError {}
+
+// a cell or organism queued to be placed at the start of the next update
+enum Spawn {
+    Cell(Cell),
+    Organism(Organism, OrganismId)
+}
+
+/// A tank of cells. Spawn cells into it, advance it with `update` and read it back with `get` or `iter`.
 pub struct World {
     cells: Cells,
-    spawns: Vec::<((usize, usize), Cell)>
+    spawns: Vec::<((usize, usize), Spawn)>,
+    tick: u64,
+    history: PopulationHistory,
+    schedule: Schedule,
+    scan_order: ScanOrder,
+    day_cycle: Option::<DayCycle>,
+    // ticks since midnight
+    day_tick: u64
 }
 
 impl World {
 
-    pub fn new() -> Self {
-        let cells = Cells::new();
+    /// Create a world full of water with a randomly seeded simulation.
+    /// Panics if `width` or `height` is zero.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::with_rng(width, height, WorldRng::from_entropy())
+    }
+
+    /// Create a world whose simulation is fully determined by `seed` and the spawns it is given.
+    /// Panics if `width` or `height` is zero.
+    pub fn with_seed(width: usize, height: usize, seed: u64) -> Self {
+        Self::with_rng(width, height, WorldRng::seed_from_u64(seed))
+    }
+
+    /// Restart the random number generator from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.cells.rng = WorldRng::seed_from_u64(seed);
+    }
+
+    fn with_rng(width: usize, height: usize, rng: WorldRng) -> Self {
+        assert!(width > 0 && height > 0, "a world must be at least 1x1, not {}x{}", width, height);
+        let cells = Cells::new(width, height, rng);
 
         Self {
             cells,
-            spawns: vec!()
+            spawns: vec!(),
+            tick: 0,
+            history: PopulationHistory::new(DEFAULT_HISTORY_LEN),
+            schedule: Schedule::Serial,
+            scan_order: ScanOrder::Fixed,
+            day_cycle: None,
+            day_tick: 0
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.height
+    }
+
+    /// Cell at `(x, y)`, or `None` outside the world.
+    pub fn get(&self, x: usize, y: usize) -> Option<&Cell> {
+        if x < self.cells.width && y < self.cells.height {
+            Some(&self.cells.inner[self.cells.pos_to_idx(x, y)])
+        }
+        else {
+            None
         }
     }
 
+    /// State of the organism whose head cell holds `handle`.
+    pub fn organism(&self, handle: Handle) -> Option<&Organism> {
+        self.cells.organism(handle)
+    }
+
+    /// Every living organism, in the order of their head cells row by row from the top left.
+    pub fn organisms(&self) -> impl Iterator<Item = OrganismInfo> + '_ {
+        self.iter().filter_map(move |(x, y, cell)| {
+            let handle = cell.handle()?;
+            let organism = self.cells.organisms.get(handle)?;
+            let life = self.cells.lives[handle.index()];
+            Some(OrganismInfo { id: life.id, species: cell.name(), position: (x, y), energy: organism.energy(), age: life.age })
+        })
+    }
+
+    /// The living organism called `id`, or `None` once it has died.
+    pub fn find_organism(&self, id: OrganismId) -> Option<OrganismInfo> {
+        self.organisms().find(|o| o.id == id)
+    }
+
+    /// Every cell with its position, row by row from the top left.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, &Cell)> {
+        let width = self.cells.width;
+        self.cells.inner.iter().enumerate().map(move |(i, c)| (i % width, i / width, c))
+    }
+
+    /// Queue `cell` to be placed at `pos` at the start of the next `update`. An organism's head
+    /// cell starts a new organism in its default state, as `spawn_organism` would.
     pub fn spawn(&mut self, pos: (usize, usize), cell: Cell) {
-        self.spawns.push((pos, cell));
+        match Organism::for_cell(&cell) {
+            Some(organism) => {
+                self.spawn_organism(pos, organism);
+            },
+            None => self.spawns.push((pos, Spawn::Cell(cell)))
+        }
+    }
+
+    /// Queue a new organism to be placed with its head at `pos` at the start of the next `update`,
+    /// and return the ID it will have. Nothing will have that ID if `pos` is outside the world.
+    pub fn spawn_organism(&mut self, pos: (usize, usize), organism: Organism) -> OrganismId {
+        let id = self.cells.new_id();
+        self.spawns.push((pos, Spawn::Organism(organism, id)));
+        id
     }
    
 
+    /// Advance the simulation by one tick.
     pub fn update(&mut self) {
+        self.advance_day();
         self.cells.set_offset(0, 0);
         self.cells.reset_ignored();
-        for ((x, y), c) in self.spawns.drain(0..).filter(|((x, y), _)| in_bounds(*x as i32, *y as i32)) {
-            self.cells.set(x as i32, y as i32, c);
+        self.cells.swap_awake();
+        for ((x, y), spawn) in self.spawns.drain(0..) {
+            if self.cells.in_bounds(x as i32, y as i32) {
+                match spawn {
+                    Spawn::Cell(c) => self.cells.set(x as i32, y as i32, c),
+                    Spawn::Organism(o, id) => self.cells.place_organism(x as i32, y as i32, o, id)
+                }
+            }
+        }
+        if self.cells.lighting_stale {
+            self.cells.calc_shadow();
+        }
+
+        match self.schedule {
+            Schedule::Checkerboard { threads } if self.cells.checkerboard_fits() => self.update_checkerboard(threads),
+            // a wrapping world whose width isn't a multiple of two regions is updated serially too
+            _ => {
+                let columns = self.cells.scan_columns(0, self.cells.width, self.scan_order, self.tick);
+                self.cells.update_area(&columns, 0, self.cells.height);
+            }
+        }
+
+        self.cells.collect_organisms();
+        self.tick += 1;
+        // counting every cell isn't free, so only when it's kept
+        if self.history.capacity() > 0 {
+            self.history.push(self.census());
+        }
+    }
+
+    /// Move the sun on by a tick.
+    fn advance_day(&mut self) {
+        if let Some(cycle) = self.day_cycle {
+            self.day_tick = (self.day_tick + 1) % cycle.length;
+            self.place_sun();
+        }
+    }
+
+    /// Set the sun's brightness, and its angle if it sweeps, for the time of day.
+    fn place_sun(&mut self) {
+        let (daylight, sun_angle) = (self.cells.daylight, self.cells.sun_angle);
+        match (self.day_cycle, self.time_of_day()) {
+            (Some(cycle), Some(phase)) => {
+                self.cells.daylight = sun::daylight(phase);
+                if cycle.sweep {
+                    self.cells.sun_angle = sun::sweep_angle(phase);
+                }
+            },
+            _ => self.cells.daylight = 1.0
         }
+        self.cells.lighting_stale |= self.cells.daylight != daylight || self.cells.sun_angle != sun_angle;
+    }
 
-        // TODO flip/flop which end of the x-axis to start on?
-        for x in 0..WORLD_WIDTH{
-            for y in (0..WORLD_HEIGHT).rev() {
-                self.cells.set_offset(0, 0);
-                if !self.cells.ignored(x as i32, y as i32) {
-                    let cell = self.cells.get(x as i32, y as i32).unwrap().clone();
-                    self.cells.set_offset(x as i32, y as i32);
-                    cell.update(&mut self.cells);
-                }                
+    fn update_checkerboard(&mut self, threads: usize) {
+        // drawn straight from the generator so no chunk is woken by it
+        let tick_seed = self.cells.rng.gen::<u64>();
+        let threads = threads.max(1);
+        let regions_x = self.cells.width.div_ceil(REGION_SIZE);
+        let regions_y = self.cells.height.div_ceil(REGION_SIZE);
+
+        for (px, py) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter().copied() {
+            let mut regions = vec!();
+            for ry in (py..regions_y).step_by(2) {
+                for rx in (px..regions_x).step_by(2) {
+                    if self.cells.region_awake(rx, ry) {
+                        let seed = tick_seed ^ ((ry * regions_x + rx) as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+                        regions.push((rx, ry, seed));
+                    }
+                }
+            }
+
+            let (cells, order, tick) = (&self.cells, self.scan_order, self.tick);
+            let update = move |group: &[(usize, usize, u64)]| -> Vec::<Cells> {
+                group.iter().map(|(rx, ry, seed)| cells.update_region(*rx, *ry, *seed, order, tick)).collect()
+            };
+            let updated = if threads == 1 || regions.len() <= 1 {
+                update(&regions)
+            }
+            else {
+                let per_thread = regions.len().div_ceil(threads);
+                thread::scope(|scope| {
+                    let workers: Vec::<_> = regions.chunks(per_thread).map(|group| scope.spawn(move || update(group))).collect();
+                    workers.into_iter().flat_map(|w| w.join().expect("region update panicked")).collect()
+                })
+            };
+
+            // merged in a fixed order, so the result doesn't depend on the number of threads
+            for ((rx, ry, _), window) in regions.into_iter().zip(updated) {
+                self.cells.merge_region(rx, ry, window);
             }
         }
     }
 
+    /// Choose how `update` visits the cells. `Schedule::Serial` by default.
+    pub fn set_schedule(&mut self, schedule: Schedule) {
+        self.schedule = schedule;
+    }
+
+    pub fn schedule(&self) -> Schedule {
+        self.schedule
+    }
+
+    /// Choose the order `update` visits the columns in. `ScanOrder::Fixed` by default, and saved
+    /// with the world.
+    pub fn set_scan_order(&mut self, order: ScanOrder) {
+        self.scan_order = order;
+    }
+
+    pub fn scan_order(&self) -> ScanOrder {
+        self.scan_order
+    }
+
+    /// Choose what lies past the left and right edges. `Boundary::Walls` by default, and saved
+    /// with the world.
+    pub fn set_boundary(&mut self, boundary: Boundary) {
+        let wrap_x = boundary == Boundary::WrapX;
+        self.cells.lighting_stale |= self.cells.wrap_x != wrap_x;
+        self.cells.wrap_x = wrap_x;
+    }
+
+    pub fn boundary(&self) -> Boundary {
+        if self.cells.wrap_x { Boundary::WrapX } else { Boundary::Walls }
+    }
+
+    /// Choose what happens to cells written outside the world. `OutOfBounds::Drop` by default.
+    pub fn set_out_of_bounds(&mut self, policy: OutOfBounds) {
+        self.cells.out_of_bounds = policy;
+    }
+
+    pub fn out_of_bounds(&self) -> OutOfBounds {
+        self.cells.out_of_bounds
+    }
+
+    /// Number of cells written outside the world and dropped since the world was created or loaded.
+    pub fn stray_writes(&self) -> u64 {
+        self.cells.stray_writes
+    }
+
+    /// Set the angle of the sunlight in degrees from straight down, up to `MAX_SUN_ANGLE` either
+    /// way. Positive angles put the sun on the left, so shadows fall to the right. 0 by default,
+    /// and saved with the world.
+    pub fn set_sun_angle(&mut self, degrees: f32) {
+        let sun_angle = if degrees.is_nan() { 0.0 } else { degrees.clamp(-MAX_SUN_ANGLE, MAX_SUN_ANGLE) };
+        self.cells.lighting_stale |= self.cells.sun_angle != sun_angle;
+        self.cells.sun_angle = sun_angle;
+    }
+
+    pub fn sun_angle(&self) -> f32 {
+        self.cells.sun_angle
+    }
+
+    /// Set how many cells either side of each cell smooth lighting averages over, up to
+    /// `MAX_LIGHT_BLUR_RADIUS`. `DEFAULT_LIGHT_BLUR_RADIUS` to begin with; 0 turns the smoothing
+    /// off. Not saved with the world.
+    pub fn set_light_blur_radius(&mut self, radius: usize) {
+        self.cells.light_blur_radius = radius.min(MAX_LIGHT_BLUR_RADIUS);
+    }
+
+    pub fn light_blur_radius(&self) -> usize {
+        self.cells.light_blur_radius
+    }
+
+    /// Turn the day and night cycle on or off. Without one, which is the default, it is always
+    /// noon. Turning it on starts the day at noon, and changing the length keeps the time of day.
+    /// Saved with the world.
+    pub fn set_day_cycle(&mut self, cycle: Option::<DayCycle>) {
+        let cycle = cycle.filter(|c| c.length > 0);
+        self.day_tick = match (self.day_cycle, cycle) {
+            (None, Some(new)) => new.length / 2,
+            (Some(old), Some(new)) => (self.day_tick as u128 * new.length as u128 / old.length as u128) as u64,
+            (_, None) => 0
+        };
+        self.day_cycle = cycle;
+        self.place_sun();
+    }
+
+    pub fn day_cycle(&self) -> Option::<DayCycle> {
+        self.day_cycle
+    }
+
+    /// How far through the day it is, from 0 at midnight through 0.5 at noon, or `None` without a
+    /// day and night cycle. `clock_time` turns it into a time.
+    pub fn time_of_day(&self) -> Option::<f32> {
+        self.day_cycle.map(|c| self.day_tick as f32 / c.length as f32)
+    }
+
+    /// Let chunks where nothing is happening sleep. On by default; turning it off updates every
+    /// cell every tick, which gives the same results more slowly.
+    pub fn set_chunk_sleeping(&mut self, enabled: bool) {
+        self.cells.sleeping = enabled;
+        if !enabled {
+            self.cells.wake_next.iter_mut().for_each(|w| *w = true);
+        }
+    }
+
+    /// Number of chunks updated in the last tick, and the total number of chunks.
+    pub fn awake_chunks(&self) -> (usize, usize) {
+        (self.cells.awake.iter().filter(|a| **a).count(), self.cells.awake.len())
+    }
+
+    /// Number of times `update` has been called, counting those before it was saved if it was loaded.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Count the cells in the world as it is now.
+    pub fn census(&self) -> Population {
+        Population::count_cells(self.tick, self.cells.inner.iter(), self.cells.organisms.iter().map(|(_, o)| o))
+    }
+
+    /// Censuses taken at the end of each recent `update`.
+    pub fn history(&self) -> &PopulationHistory {
+        &self.history
+    }
+
+    pub fn history_mut(&mut self) -> &mut PopulationHistory {
+        &mut self.history
+    }
+
+    /// Render the world into `fb`, an RGBA buffer of `width * height` pixels. Drawing doesn't
+    /// change how the world goes on, smoothed or not.
     pub fn draw(&mut self, fb : &mut [u8], smooth_lighting: bool) {
         self.cells.draw(fb, smooth_lighting);
     }
-}
\ No newline at end of file
+
+    /// Build a world from a text scene, one character per cell as given by `Cell::to_char`, with
+    /// organisms in their default state.
+    /// Blank lines are skipped, every other line is a row and all rows must be the same width.
+    pub fn from_ascii(text: &str) -> Result<Self, AsciiError> {
+        Self::from_ascii_with_rng(text, WorldRng::from_entropy())
+    }
+
+    /// Build a world from a text scene as `from_ascii` does, whose simulation is fully determined
+    /// by `seed` as with `with_seed`.
+    pub fn from_ascii_seeded(text: &str, seed: u64) -> Result<Self, AsciiError> {
+        Self::from_ascii_with_rng(text, WorldRng::seed_from_u64(seed))
+    }
+
+    fn from_ascii_with_rng(text: &str, rng: WorldRng) -> Result<Self, AsciiError> {
+        let rows: Vec::<Vec::<char>> = text
+            .lines()
+            .map(|l| l.trim_end().chars().collect::<Vec::<char>>())
+            .filter(|l| !l.is_empty())
+            .collect();
+
+        let width = rows.first().ok_or(AsciiError::Empty)?.len();
+        let mut world = Self::with_rng(width, rows.len(), rng);
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(AsciiError::RaggedRow { row: y, width: row.len(), expected: width });
+            }
+            for (x, c) in row.iter().enumerate() {
+                let cell = match (Cell::from_char(*c), Organism::from_char(*c)) {
+                    (Some(cell), _) => cell,
+                    (None, Some(organism)) => {
+                        let handle = world.cells.organisms.insert(organism);
+                        let id = world.cells.new_id();
+                        world.cells.set_life(handle, Life { id, age: 0 });
+                        world.cells.organisms.get(handle).unwrap().cell(handle)
+                    },
+                    (None, None) => return Err(AsciiError::UnknownChar { row: y, column: x, c: *c })
+                };
+                let i = world.cells.pos_to_idx(x, y);
+                world.cells.inner[i] = cell;
+            }
+        }
+        Ok(world)
+    }
+
+    /// The world as a text scene that `from_ascii` reads back. Organism state is not included.
+    pub fn to_ascii(&self) -> String {
+        let mut text = String::with_capacity((self.cells.width + 1) * self.cells.height);
+        for row in self.cells.inner.chunks(self.cells.width) {
+            text.extend(row.iter().map(Cell::to_char));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Write a snapshot of the whole world, including how far through its random numbers it is,
+    /// so a loaded world carries on exactly like this one does.
+    pub fn save(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(save::MAGIC)?;
+        save::write_u32(w, save::VERSION)?;
+        save::write_u32(w, self.cells.width as u32)?;
+        save::write_u32(w, self.cells.height as u32)?;
+        w.write_all(&self.cells.rng.seed())?;
+        save::write_u128(w, self.cells.rng.word_pos())?;
+        save::write_u64(w, self.tick)?;
+        save::write_u8(w, self.scan_order.tag())?;
+        save::write_u8(w, self.cells.wrap_x as u8)?;
+        save::write_u64(w, self.cells.next_id)?;
+        save::write_u32(w, self.cells.sun_angle.to_bits())?;
+        // a day length of 0 means there is no cycle
+        save::write_u64(w, self.day_cycle.map_or(0, |c| c.length))?;
+        save::write_u8(w, matches!(self.day_cycle, Some(DayCycle { sweep: true, .. })) as u8)?;
+        save::write_u64(w, self.day_tick)?;
+        for cell in &self.cells.inner {
+            cell.write(w, &self.cells.organisms)?;
+            if let Some(handle) = cell.handle() {
+                let life = self.cells.lives[handle.index()];
+                save::write_u64(w, life.id.0)?;
+                save::write_u64(w, life.age)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a world written by `World::save`. Its lighting is worked out again.
+    pub fn load(r: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != save::MAGIC {
+            return Err(save::invalid("not a fishtank save"));
+        }
+        let version = save::read_u32(r)?;
+        if version != save::VERSION {
+            return Err(save::invalid(&format!("unsupported save version {}", version)));
+        }
+
+        let width = save::read_u32(r)? as usize;
+        let height = save::read_u32(r)? as usize;
+        if width == 0 || height == 0 || width.checked_mul(height).filter(|&n| n <= 1 << 26).is_none() {
+            return Err(save::invalid("bad world dimensions"));
+        }
+        let mut seed = [0u8; 32];
+        r.read_exact(&mut seed)?;
+        let rng = WorldRng::resume(seed, save::read_u128(r)?);
+        let tick = save::read_u64(r)?;
+        let scan_order = ScanOrder::from_tag(save::read_u8(r)?).ok_or_else(|| save::invalid("unknown scan order"))?;
+        let wrap_x = save::read_u8(r)? != 0;
+        let next_id = save::read_u64(r)?;
+        let sun_angle = f32::from_bits(save::read_u32(r)?);
+        let length = save::read_u64(r)?;
+        let sweep = save::read_u8(r)? != 0;
+        let day_tick = save::read_u64(r)?;
+        if length > 0 && day_tick >= length {
+            return Err(save::invalid("time of day past the end of the day"));
+        }
+        let day_cycle = Some(DayCycle { length, sweep }).filter(|c| c.length > 0);
+
+        let mut world = Self::with_rng(width, height, rng);
+        world.tick = tick;
+        world.scan_order = scan_order;
+        world.cells.wrap_x = wrap_x;
+        world.set_sun_angle(sun_angle);
+        world.day_cycle = day_cycle;
+        world.day_tick = day_tick;
+        world.place_sun();
+        for i in 0..world.cells.inner.len() {
+            let cell = Cell::read(r, &mut world.cells.organisms)?;
+            if let Some(handle) = cell.handle() {
+                let life = Life { id: OrganismId(save::read_u64(r)?), age: save::read_u64(r)? };
+                world.cells.set_life(handle, life);
+            }
+            world.cells.inner[i] = cell;
+        }
+        world.cells.next_id = next_id;
+        Ok(world)
+    }
+
+    /// Render the world into a new RGBA buffer, as `draw` does.
+    pub fn render_to_rgba(&mut self, smooth_lighting: bool) -> Vec::<u8> {
+        let mut fb = vec![0u8; self.cells.width * self.cells.height * 4];
+        self.draw(&mut fb, smooth_lighting);
+        fb
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small tank with sand, stone and a few of every organism, run for `ticks` ticks.
+    fn stocked(seed: u64, ticks: usize) -> World {
+        let mut world = World::with_seed(48, 32, seed);
+        for x in 0..48 {
+            for y in 28..32 {
+                world.spawn((x, y), Cell::Sand);
+            }
+        }
+        for x in 20..24 {
+            world.spawn((x, 27), Cell::Stone);
+        }
+        for i in 0..6 {
+            world.spawn_organism((4 + i * 7, 8), Organism::new_fish());
+            world.spawn_organism((2 + i * 7, 27), Organism::new_plant());
+            world.spawn_organism((5 + i * 7, 4), Organism::new_algae());
+            world.spawn((6 + i * 7, 27), Cell::BottomFeeder);
+        }
+        world.spawn_organism((30, 20), Organism::new_worm());
+        world.spawn((10, 27), Cell::Fizzer);
+        for _ in 0..ticks {
+            world.update();
+        }
+        world
+    }
+
+    #[test]
+    fn same_seed_same_tank() {
+        let a = stocked(7, 300);
+        let b = stocked(7, 300);
+        assert_eq!(a.to_ascii(), b.to_ascii());
+        assert_eq!(a.census(), b.census());
+    }
+
+    #[test]
+    fn drawing_doesnt_change_the_tank() {
+        let mut a = stocked(3, 0);
+        let mut b = stocked(3, 0);
+        a.set_day_cycle(Some(DayCycle { length: 100, sweep: true }));
+        b.set_day_cycle(Some(DayCycle { length: 100, sweep: true }));
+        for tick in 0..300 {
+            a.update();
+            b.update();
+            if tick % 3 == 0 {
+                b.render_to_rgba(tick % 2 == 0);
+            }
+        }
+        assert_eq!(a.to_ascii(), b.to_ascii());
+        assert_eq!(a.census(), b.census());
+    }
+
+    #[test]
+    fn loaded_world_carries_on_the_same() {
+        // an odd tick, so the next one is scanned right to left
+        let mut world = stocked(5, 151);
+        world.set_scan_order(ScanOrder::Alternating);
+        let mut saved = vec!();
+        world.save(&mut saved).unwrap();
+        let mut loaded = World::load(&mut saved.as_slice()).unwrap();
+        for _ in 0..150 {
+            world.update();
+            loaded.update();
+        }
+        assert_eq!(world.to_ascii(), loaded.to_ascii());
+        assert_eq!(world.census(), loaded.census());
+    }
+
+    const SCENE: &str = "\
+~~~~~~~~~~~~~~~~
+~~~a~~~~~~~~a~~~
+~~~~~~f~~~~~~~~~
+~~~~~~~~~~~~~~~~
+~~~~~~~~~~w~~~~~
+~~~~~~~~~~~~~~~~
+~~p~~~~#~~~~~b~~
+...*..z...k.....
+################
+";
+
+    #[test]
+    fn ascii_round_trip() {
+        let scene: String = SCENE.lines().map(|l| format!("{}\n", l)).collect();
+        let world = World::from_ascii(&scene).unwrap();
+        assert_eq!(world.to_ascii(), scene);
+        assert_eq!(World::from_ascii(&world.to_ascii()).unwrap().to_ascii(), scene);
+    }
+
+    #[test]
+    fn ascii_round_trip_after_running() {
+        let world = stocked(11, 200);
+        let text = world.to_ascii();
+        assert_eq!(World::from_ascii(&text).unwrap().to_ascii(), text);
+    }
+
+    #[test]
+    fn seeded_scene_repeats() {
+        let run = || {
+            let mut world = World::from_ascii_seeded(SCENE, 4).unwrap();
+            for _ in 0..200 {
+                world.update();
+            }
+            world.to_ascii()
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn ascii_errors() {
+        assert_eq!(World::from_ascii("\n\n").err(), Some(AsciiError::Empty));
+        assert_eq!(World::from_ascii("~~~\n~~\n").err(), Some(AsciiError::RaggedRow { row: 1, width: 2, expected: 3 }));
+        assert_eq!(World::from_ascii("~~~\n~?~\n").err(), Some(AsciiError::UnknownChar { row: 1, column: 1, c: '?' }));
+    }
+
+    #[test]
+    fn ignored_cells_reset_each_tick() {
+        let mut cells = Cells::new(4, 4, WorldRng::seed_from_u64(0));
+        cells.ignore(1, 2);
+        assert!(cells.ignored(1, 2));
+        assert!(!cells.ignored(2, 1));
+        cells.reset_ignored();
+        assert!(!cells.ignored(1, 2));
+    }
+
+    #[test]
+    fn stamps_survive_generation_wraparound() {
+        let mut cells = Cells::new(4, 4, WorldRng::seed_from_u64(0));
+        // stamped 2^32 - 1 ticks ago, so it would look current once the generation wraps to 1
+        cells.ignore(3, 3);
+        cells.generation = u32::MAX;
+        cells.ignore(1, 2);
+        assert!(cells.ignored(1, 2));
+        assert!(!cells.ignored(3, 3));
+
+        cells.reset_ignored();
+        assert_eq!(cells.generation, 1);
+        assert!(!cells.ignored(1, 2));
+        assert!(!cells.ignored(3, 3));
+        cells.ignore(0, 0);
+        assert!(cells.ignored(0, 0));
+    }
+
+    /// A tall tank with life only near the bottom, so the water above can sleep.
+    fn settling(seed: u64, sleeping: bool, schedule: Schedule) -> World {
+        let mut world = World::with_seed(64, 80, seed);
+        world.set_chunk_sleeping(sleeping);
+        world.set_schedule(schedule);
+        for x in 0..64 {
+            world.spawn((x, 79), Cell::Stone);
+            for y in 60..64 {
+                world.spawn((x, y), Cell::Sand);
+            }
+        }
+        for i in 0..4 {
+            world.spawn_organism((8 + i * 12, 74), Organism::new_fish());
+            world.spawn_organism((4 + i * 12, 70), Organism::new_algae());
+            world.spawn((6 + i * 12, 78), Cell::BottomFeeder);
+        }
+        world
+    }
+
+    #[test]
+    fn sleeping_doesnt_change_the_tank() {
+        for schedule in [Schedule::Serial, Schedule::Checkerboard { threads: 2 }].iter().copied() {
+            let mut awake = settling(21, false, schedule);
+            let mut sleepy = settling(21, true, schedule);
+            let mut slept = false;
+            for _ in 0..400 {
+                awake.update();
+                sleepy.update();
+                let (woken, chunks) = sleepy.awake_chunks();
+                slept |= woken < chunks;
+            }
+            assert!(slept);
+            assert_eq!(awake.to_ascii(), sleepy.to_ascii());
+            assert_eq!(awake.census(), sleepy.census());
+        }
+    }
+
+    #[test]
+    fn sand_beds_sleep() {
+        for schedule in [Schedule::Serial, Schedule::Checkerboard { threads: 2 }].iter().copied() {
+            let mut world = World::with_seed(64, 64, 8);
+            world.set_schedule(schedule);
+            for y in 32..64 {
+                for x in 0..64 {
+                    world.spawn((x, y), Cell::Sand);
+                }
+            }
+            for _ in 0..200 {
+                world.update();
+            }
+            assert_eq!(world.awake_chunks().0, 0);
+        }
+    }
+
+    #[test]
+    fn checkerboard_is_the_same_on_any_number_of_threads() {
+        let run = |threads| {
+            let mut world = settling(17, true, Schedule::Checkerboard { threads });
+            world.set_scan_order(ScanOrder::Shuffled);
+            for _ in 0..300 {
+                world.update();
+            }
+            (world.to_ascii(), world.census())
+        };
+        let one = run(1);
+        assert_eq!(one, run(2));
+        assert_eq!(one, run(3));
+        assert_eq!(one, run(8));
+    }
+
+    #[test]
+    fn scan_orders() {
+        let mut cells = Cells::new(6, 1, WorldRng::seed_from_u64(2));
+        assert_eq!(cells.scan_columns(1, 5, ScanOrder::Fixed, 3), vec![1, 2, 3, 4]);
+        assert_eq!(cells.scan_columns(1, 5, ScanOrder::Alternating, 4), vec![1, 2, 3, 4]);
+        assert_eq!(cells.scan_columns(1, 5, ScanOrder::Alternating, 5), vec![4, 3, 2, 1]);
+
+        // the same for the same seed, and every column once
+        let shuffled = cells.scan_columns(0, 6, ScanOrder::Shuffled, 0);
+        assert_eq!(shuffled, Cells::new(6, 1, WorldRng::seed_from_u64(2)).scan_columns(0, 6, ScanOrder::Shuffled, 0));
+        let mut sorted = shuffled.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..6).collect::<Vec::<usize>>());
+    }
+
+    #[test]
+    fn scan_order_tags() {
+        for order in [ScanOrder::Fixed, ScanOrder::Alternating, ScanOrder::Shuffled].iter().copied() {
+            assert_eq!(ScanOrder::from_tag(order.tag()), Some(order));
+        }
+        assert_eq!(ScanOrder::from_tag(3), None);
+    }
+
+    #[test]
+    fn neighbours_wrap_both_ways() {
+        let mut cells = Cells::new(5, 3, WorldRng::seed_from_u64(0));
+        cells.wrap_x = true;
+        cells.set_offset(4, 1);
+        cells.set(1, 0, Cell::Stone);
+        assert_eq!(cells.inner[cells.pos_to_idx(0, 1)], Cell::Stone);
+        cells.set_offset(0, 1);
+        assert_eq!(cells.get(0, 0), Some(&Cell::Stone));
+        assert_eq!(cells.get(5, 0), Some(&Cell::Stone));
+        cells.swap(0, 0, -1, 0);
+        assert_eq!(cells.inner[cells.pos_to_idx(4, 1)], Cell::Stone);
+        cells.set_offset(4, 1);
+        assert_eq!(cells.get(0, 0), Some(&Cell::Stone));
+        assert_eq!(cells.get(-5, 0), Some(&Cell::Stone));
+        // the top and bottom never wrap
+        assert_eq!(cells.get(0, 2), None);
+    }
+
+    #[test]
+    fn wrapped_lighting_moves_with_the_tank() {
+        let scene = "\
+~~~~~~~~~~~~~~~~
+~~#~~~~~~~~~~~##
+~~~~~~~~~~~~~~~~
+~~~~~~#~~~~~~~~~
+~~~~~~~~~~~~~~~~
+~~~~~~~~~~~~~~~~
+";
+        // the same tank turned round by five columns
+        let shifted: String = scene.lines().map(|l| format!("{}{}\n", &l[11..], &l[..11])).collect();
+        for angle in [0.0, 30.0, -60.0].iter().copied() {
+            let mut a = World::from_ascii_seeded(scene, 0).unwrap();
+            let mut b = World::from_ascii_seeded(&shifted, 0).unwrap();
+            for world in [&mut a, &mut b].iter_mut() {
+                world.set_boundary(Boundary::WrapX);
+                world.set_sun_angle(angle);
+                world.cells.calc_shadow();
+            }
+            for y in 0..6 {
+                for x in 0..16 {
+                    let moved = (x + 5) % 16;
+                    assert_eq!(a.cells.lighting[y * 16 + x], b.cells.lighting[y * 16 + moved]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn spawned_head_cells_start_organisms() {
+        let mut world = World::with_seed(8, 8, 0);
+        world.spawn((2, 2), Cell::new_fish());
+        world.spawn((5, 7), Cell::Plant { handle: Handle::DETACHED });
+        world.update();
+        let mut species: Vec::<_> = world.organisms().map(|o| o.species).collect();
+        species.sort_unstable();
+        assert_eq!(species, vec!["Fish", "Plant"]);
+        let handle = world.get(5, 7).and_then(Cell::handle).unwrap();
+        assert!(matches!(world.organism(handle), Some(Organism::Plant(_))));
+    }
+
+    #[test]
+    fn organisms_moved_outside_stay_put() {
+        let mut cells = Cells::new(4, 4, WorldRng::seed_from_u64(0));
+        let handle = cells.organisms.insert(Organism::new_fish());
+        cells.set_offset(0, 2);
+        let fish = cells.take_organism(handle).unwrap();
+        let fish = cells.set_organism(-1, 0, handle, fish).unwrap_err();
+        assert_eq!(cells.stray_writes, 1);
+        cells.move_organism(-1, 0, handle, fish);
+        assert_eq!(cells.get(0, 0), Some(&Cell::Fish { handle }));
+        assert_eq!(cells.organism(handle), Some(&Organism::new_fish()));
+
+        let fish = cells.take_organism(handle).unwrap();
+        assert!(cells.set_organism(1, 0, handle, fish).is_ok());
+        assert_eq!(cells.get(1, 0), Some(&Cell::Fish { handle }));
+    }
+
+    #[test]
+    fn slow_days_relight_now_and_then() {
+        let mut world = World::new(16, 16);
+        world.set_day_cycle(Some(DayCycle { length: 1_000_000, sweep: true }));
+        world.update();
+        let mut relit = 0;
+        for _ in 0..10_000 {
+            world.advance_day();
+            relit += world.cells.lighting_stale as usize;
+            world.cells.calc_shadow();
+        }
+        // a hundredth of a day moves the sun by a degree or so
+        assert!(relit > 0 && relit <= 5, "relit {} times", relit);
+    }
+
+    #[test]
+    fn algae_shade_plants() {
+        let mut world = World::from_ascii_seeded("\
+~~~~~~~~
+aaaa~~~~
+aaaa~~~~
+~~~~~~~~
+~~~~~~~~
+~~~~~~~~
+~p~~~~p~
+########
+", 1).unwrap();
+        world.cells.calc_shadow();
+        let light = |x: usize, y: usize| world.cells.lighting[world.cells.pos_to_idx(x, y)];
+        // two layers of algae take about three levels, the water above the other plant almost none
+        assert_eq!(light(6, 6), 15);
+        assert_eq!(light(1, 6), 12);
+        assert!(light(1, 3) < light(1, 0));
+    }
+
+    // a world of water lit with noise, to blur
+    fn noisy_light(width: usize, height: usize, radius: usize) -> World {
+        let mut rng = WorldRng::seed_from_u64(4);
+        let mut world = World::new(width, height);
+        world.cells.lighting = (0..width * height).map(|_| rng.gen_range(0..16)).collect();
+        world.set_light_blur_radius(radius);
+        world
+    }
+
+    #[test]
+    fn blur_matches_averaging_each_square() {
+        let mut world = noisy_light(13, 9, 2);
+        world.cells.blur_lighting();
+        let cells = &world.cells;
+        for y in 0..9i32 {
+            for x in 0..13i32 {
+                // off the edges, the edge cell is repeated
+                let sum: u32 = (-2..=2).flat_map(|dy| (-2..=2).map(move |dx| (dx, dy)))
+                    .map(|(dx, dy)| cells.lighting[cells.pos_to_idx((x + dx).clamp(0, 12) as usize, (y + dy).clamp(0, 8) as usize)] as u32)
+                    .sum();
+                let expected = ((sum as f32) / 25.0).round() as u8;
+                assert_eq!(cells.lighting_tmp[cells.pos_to_idx(x as usize, y as usize)], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn blur_is_symmetric() {
+        let mut world = noisy_light(13, 9, 3);
+        let mut mirrored = noisy_light(13, 9, 3);
+        for y in 0..9 {
+            for x in 0..13 {
+                mirrored.cells.lighting[y * 13 + x] = world.cells.lighting[y * 13 + 12 - x];
+            }
+        }
+        world.cells.blur_lighting();
+        mirrored.cells.blur_lighting();
+        for y in 0..9 {
+            for x in 0..13 {
+                assert_eq!(mirrored.cells.lighting_tmp[y * 13 + x], world.cells.lighting_tmp[y * 13 + 12 - x]);
+            }
+        }
+    }
+
+    #[test]
+    fn no_blur_draws_the_lighting_as_it_is() {
+        let mut world = noisy_light(13, 9, 0);
+        world.cells.lighting_stale = false;
+        let lighting = world.cells.lighting.clone();
+        let (smooth, rough) = (world.render_to_rgba(true), world.render_to_rgba(false));
+        assert_eq!(smooth, rough);
+        assert_eq!(world.cells.lighting, lighting);
+        // and a blur over no neighbours leaves it alone
+        world.cells.blur_lighting();
+        assert_eq!(world.cells.lighting_tmp, lighting);
+    }
+
+    #[test]
+    fn narrow_wrapped_worlds_light_each_cell_once() {
+        // a lamp in the dark, in a wrapping world narrower than its light and in a wide one
+        let lit = |width: usize| {
+            let row = |c: char| -> String { (0..width).map(|x| if x == 3 { c } else { '~' }).collect() };
+            let text: String = (0..24).map(|y| row(if y == 10 { '*' } else { '~' }) + "\n").collect();
+            let mut world = World::from_ascii_seeded(&text, 1).unwrap();
+            world.set_boundary(Boundary::WrapX);
+            world.set_day_cycle(Some(DayCycle { length: 100, sweep: false }));
+            world.day_tick = 0;
+            world.place_sun();
+            world.cells.calc_shadow();
+            world
+        };
+        let (narrow, wide) = (lit(8), lit(64));
+        for y in 0..24 {
+            for dx in -3i32..=4 {
+                let (nx, wx) = ((3 + dx).rem_euclid(8) as usize, (3 + dx).rem_euclid(64) as usize);
+                assert_eq!(narrow.cells.lighting[narrow.cells.pos_to_idx(nx, y)], wide.cells.lighting[wide.cells.pos_to_idx(wx, y)]);
+            }
+        }
+    }
+
+    #[test]
+    fn organisms_keep_their_ids() {
+        let mut world = World::with_seed(32, 24, 6);
+        world.set_boundary(Boundary::WrapX);
+        for x in 0..32 {
+            world.spawn((x, 23), Cell::Sand);
+        }
+        world.spawn_organism((4, 8), Organism::new_algae());
+        world.spawn_organism((20, 16), Organism::new_worm());
+        let id = world.spawn_organism((10, 12), Organism::new_fish());
+        let mut last = None;
+        let mut moved = false;
+        for _ in 0..500 {
+            world.update();
+            let fish = world.find_organism(id).unwrap();
+            assert_eq!(fish.species, "Fish");
+            if let Some(last) = last.replace(fish.clone()) {
+                assert_eq!(fish.age, last.age + 1);
+                moved |= fish.position != last.position;
+            }
+            let mut ids: Vec::<_> = world.organisms().map(|o| o.id).collect();
+            ids.sort_unstable();
+            ids.dedup();
+            assert_eq!(ids.len(), world.organisms().count());
+        }
+        assert!(moved);
+
+        let mut saved = vec!();
+        world.save(&mut saved).unwrap();
+        let mut loaded = World::load(&mut saved.as_slice()).unwrap();
+        assert_eq!(loaded.find_organism(id), last);
+        for _ in 0..20 {
+            world.update();
+            loaded.update();
+        }
+        assert_eq!(loaded.find_organism(id), world.find_organism(id));
+        assert_eq!(loaded.find_organism(id).unwrap().age, last.unwrap().age + 20);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_width_is_rejected() {
+        World::new(0, 16);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_height_is_rejected() {
+        World::new(16, 0);
+    }
+}